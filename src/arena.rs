@@ -0,0 +1,161 @@
+use crate::{enemy::Enemy, simulation::SimulationStage, Position, Velocity};
+use bevy::{prelude::*, render::camera::Camera2d};
+use bevy_prototype_lyon::prelude::*;
+
+pub(crate) struct ArenaPlugin;
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DEFAULT_ARENA);
+        app.add_startup_system(add_letterbox);
+        app.add_system(fit_camera_system);
+        app.add_system(update_letterbox);
+        app.add_system_to_stage(SimulationStage, bounce_enemies);
+    }
+}
+
+/// The playable area of a stage, centered at the origin.
+///
+/// Gameplay systems refer to this instead of the window size, so that a player with a larger
+/// monitor doesn't get a larger (and easier) arena. The camera is scaled to fit the arena in
+/// the window and the rest is letterboxed.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ArenaBounds {
+    pub width: f32,
+    pub height: f32,
+}
+
+pub(crate) const DEFAULT_ARENA: ArenaBounds = ArenaBounds::new(1280., 720.);
+
+impl ArenaBounds {
+    pub(crate) const fn new(width: f32, height: f32) -> Self {
+        Self { width, height }
+    }
+
+    pub(crate) fn half_size(&self) -> Vec2 {
+        Vec2::new(self.width / 2., self.height / 2.)
+    }
+
+    pub(crate) fn contains(&self, position: Vec2) -> bool {
+        let half = self.half_size();
+        -half.x <= position.x
+            && position.x <= half.x
+            && -half.y <= position.y
+            && position.y <= half.y
+    }
+
+    pub(crate) fn clamp(&self, position: Vec2) -> Vec2 {
+        let half = self.half_size();
+        position.clamp(-half, half)
+    }
+
+    /// World units per window pixel that fits the whole arena in the window.
+    pub(crate) fn scale_for(&self, window: &Window) -> f32 {
+        (self.width / window.width()).max(self.height / window.height())
+    }
+
+    /// Convert a cursor position in window coordinates to world coordinates.
+    pub(crate) fn window_to_world(&self, window: &Window, cursor: Vec2) -> Vec2 {
+        let window_size = Vec2::new(window.width(), window.height());
+        (cursor - window_size / 2.) * self.scale_for(window)
+    }
+}
+
+fn fit_camera_system(
+    windows: Res<Windows>,
+    arena: Res<ArenaBounds>,
+    mut query: Query<&mut OrthographicProjection, With<Camera2d>>,
+) {
+    let window = if let Some(window) = windows.get_primary() {
+        window
+    } else {
+        return;
+    };
+    let scale = arena.scale_for(window);
+    for mut projection in query.iter_mut() {
+        // Avoid triggering change detection every frame
+        if projection.scale != scale {
+            projection.scale = scale;
+        }
+    }
+}
+
+#[derive(Component)]
+struct Letterbox(usize);
+
+#[derive(Component)]
+struct ArenaBorder;
+
+const LETTERBOX_COLOR: Color = Color::rgb(0.05, 0.05, 0.1);
+
+/// Large enough to cover any window beyond the arena edges.
+const LETTERBOX_SIZE: f32 = 10000.;
+
+fn add_letterbox(mut commands: Commands) {
+    for i in 0..4 {
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: LETTERBOX_COLOR,
+                    custom_size: Some(Vec2::splat(LETTERBOX_SIZE)),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(Letterbox(i));
+    }
+
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &shapes::Rectangle::default(),
+            DrawMode::Stroke(StrokeMode::new(Color::rgba(0.5, 0.5, 0.8, 0.5), 2.0)),
+            Transform::from_xyz(0., 0., 0.9),
+        ))
+        .insert(ArenaBorder);
+}
+
+fn update_letterbox(
+    arena: Res<ArenaBounds>,
+    mut query: Query<(&mut Transform, &Letterbox)>,
+    mut query_border: Query<&mut Path, With<ArenaBorder>>,
+) {
+    if !arena.is_changed() {
+        return;
+    }
+
+    let half = arena.half_size();
+    let offset = LETTERBOX_SIZE / 2.;
+    for (mut transform, letterbox) in query.iter_mut() {
+        let (x, y) = match letterbox.0 {
+            0 => (-half.x - offset, 0.),
+            1 => (half.x + offset, 0.),
+            2 => (0., -half.y - offset),
+            _ => (0., half.y + offset),
+        };
+        *transform = Transform::from_xyz(x, y, 0.9);
+    }
+
+    if let Ok(mut path) = query_border.get_single_mut() {
+        *path = ShapePath::build_as(&shapes::Rectangle {
+            extents: Vec2::new(arena.width, arena.height),
+            origin: RectangleOrigin::Center,
+        });
+    }
+}
+
+/// Keep enemies inside the arena by reflecting their velocity at the edges.
+fn bounce_enemies(
+    arena: Res<ArenaBounds>,
+    mut query: Query<(&mut Position, &mut Velocity), With<Enemy>>,
+) {
+    let half = arena.half_size();
+    for (mut position, mut velocity) in query.iter_mut() {
+        if position.0.x < -half.x && velocity.x < 0. || half.x < position.0.x && 0. < velocity.x {
+            velocity.x = -velocity.x;
+        }
+        if position.0.y < -half.y && velocity.y < 0. || half.y < position.0.y && 0. < velocity.y {
+            velocity.y = -velocity.y;
+        }
+        position.0 = arena.clamp(position.0);
+    }
+}
@@ -1,23 +1,38 @@
 mod missile;
+mod pattern;
 
-use self::missile::{missile_system, Missile, MISSILE_SPEED};
+pub(crate) use self::{
+    missile::SplashEvent,
+    pattern::{BulletPattern, PatternKind, PatternSpec},
+};
+
+use self::missile::{
+    missile_system, splash_damage_system, splash_radius_by_level, Missile, MISSILE_SPEED,
+};
 use crate::{
-    can_update, sprite_transform_single,
-    tower::{MissileShooter, Shotgun, TempEnt, Tower},
+    arena::ArenaBounds,
+    damage::{apply_damage, Armor, DamageType, Resistances, Shield},
+    floating_text::FloatingTextEvent,
+    map::CurrentMap,
+    simulation::{GameRng, SimTime, SimulationStage},
+    sound::{Sound, SoundEvent},
+    sprite_transform_single,
+    stats::{Stat, Stats},
+    status_effect::{StatusEffect, StatusEffects},
+    tower::{MissileShooter, Shotgun, TempEnt, Tower, TowerLevel},
     BulletFilter, Explosion, Health, Position, Rotation, Scoreboard, StageClear, Target, Textures,
     Velocity,
 };
-use bevy::{prelude::*, sprite::collide_aabb::collide};
+use bevy::{ecs::system::EntityCommands, prelude::*, sprite::collide_aabb::collide};
 use bevy_prototype_lyon::prelude::*;
 
 pub(crate) const ENEMY_SIZE: f32 = 20.;
 const BULLET_SIZE: f32 = 20.;
 
 pub(crate) const SHOOT_INTERVAL: f32 = 0.25;
-const ENEMY_SHOOT_INTERVAL: f32 = 0.5;
 const SHOTGUN_SHOOT_INTERVAL: f32 = 0.75;
 const MISSILE_SHOOT_INTERVAL: f32 = 2.5;
-const BULLET_SPEED: f32 = 500.;
+pub(crate) const BULLET_SPEED: f32 = 500.;
 
 pub(crate) struct GainExpEvent {
     pub entity: Entity,
@@ -32,14 +47,16 @@ impl Plugin for BulletPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(ShapePlugin);
         app.add_event::<GainExpEvent>();
-        app.add_system_set(
+        app.add_event::<SplashEvent>();
+        app.add_system_set_to_stage(
+            SimulationStage,
             SystemSet::new()
-                .with_run_criteria(can_update)
                 .with_system(shoot_bullet)
                 .with_system(bullet_collision_system)
-                .with_system(missile_system),
+                .with_system(missile_system)
+                .with_system(splash_damage_system),
         );
-        app.add_system(cleanup);
+        app.add_system_to_stage(SimulationStage, cleanup);
     }
 }
 
@@ -48,14 +65,25 @@ pub(crate) struct BulletShooter {
     pub enabled: bool,
     pub cooldown: f32,
     pub damage: f32,
+    pub damage_type: DamageType,
+    /// Effect applied to the target on every hit
+    pub status_effect: Option<StatusEffect>,
+    /// Number of targets the bullets can pass through
+    pub pierce: usize,
+    /// Number of times the bullets bounce toward another target
+    pub ricochet: usize,
 }
 
 impl BulletShooter {
-    pub(crate) fn new(enabled: bool, damage: f32) -> Self {
+    pub(crate) fn new(enabled: bool, damage: f32, damage_type: DamageType) -> Self {
         Self {
             enabled,
             cooldown: 0.,
             damage,
+            damage_type,
+            status_effect: None,
+            pierce: 0,
+            ricochet: 0,
         }
     }
 }
@@ -65,12 +93,28 @@ pub(crate) struct Bullet {
     filter: bool,
     owner: Entity,
     damage: f32,
+    damage_type: DamageType,
+    status_effect: Option<StatusEffect>,
 }
 
+/// A bullet with this component passes through this number of targets before it is consumed.
+#[derive(Component)]
+pub(crate) struct Pierce(pub usize);
+
+/// A bullet with this component redirects toward the nearest other target on hit,
+/// this number of times.
+#[derive(Component)]
+pub(crate) struct Ricochet(pub usize);
+
+/// Targets that a piercing or ricocheting bullet has already hit, so that it won't hit
+/// the same one twice.
+#[derive(Component, Default)]
+pub(crate) struct HitTargets(Vec<Entity>);
+
 pub(crate) fn shoot_bullet(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    time: Res<Time>,
+    time: Res<SimTime>,
     mut query: Query<(
         Entity,
         &Position,
@@ -80,7 +124,14 @@ pub(crate) fn shoot_bullet(
         Option<&Shotgun>,
         Option<&MissileShooter>,
         Option<&Target>,
+        Option<&StatusEffects>,
+        Option<&TowerLevel>,
+        Option<&Stats>,
+        Option<&mut BulletPattern>,
     )>,
+    target_query: Query<&Position>,
+    mut sound_writer: EventWriter<SoundEvent>,
+    mut rng: ResMut<GameRng>,
 ) {
     let delta = time.delta_seconds();
     for (
@@ -92,59 +143,95 @@ pub(crate) fn shoot_bullet(
         shotgun,
         missile_shooter,
         target,
+        status_effects,
+        tower_level,
+        stats,
+        mut pattern,
     ) in query.iter_mut()
     {
-        if !bullet_shooter.enabled {
+        // Towers derive their damage and fire rate from stats, while enemies use the base values
+        let damage = stats
+            .map(|stats| stats.get(Stat::Damage))
+            .unwrap_or(bullet_shooter.damage);
+        // A higher fire rate lets the cooldown elapse faster
+        let delta = delta * stats.map(|stats| stats.get(Stat::FireRate)).unwrap_or(1.);
+        if let Some(ref mut pattern) = pattern {
+            pattern.update(delta);
+        }
+        if !bullet_shooter.enabled
+            || status_effects
+                .map(|status_effects| status_effects.is_stunned())
+                .unwrap_or(false)
+        {
             continue;
         }
         if bullet_shooter.cooldown < delta {
-            let mut shoot =
-                |file, angle: f64, speed: f32, horz_offset: f32, target: Option<Entity>| {
-                    let bullet_rotation = Rotation(angle);
-                    let mut transform = default();
-                    let position = Position(
-                        position.0
-                            + Vec2::new(
-                                angle.sin() as f32 * horz_offset,
-                                -angle.cos() as f32 * horz_offset,
-                            ),
-                    );
-
-                    let trail =
-                        missile_shooter.map(|_| missile::gen_trail(&mut commands, &position));
-
-                    sprite_transform_single(&position, Some(&bullet_rotation), &mut transform, 0.);
-                    let sprite = commands
-                        .spawn_bundle(SpriteBundle {
-                            texture: asset_server.load(file),
-                            transform: Transform::from_scale(Vec3::ONE * 3.),
-                            ..default()
-                        })
-                        .id();
-
-                    let mut builder = commands.spawn();
-                    builder.insert(Bullet {
+            let mut shoot = |file,
+                             angle: f64,
+                             speed: f32,
+                             horz_offset: f32,
+                             target: Option<Entity>| {
+                let position = Position(
+                    position.0
+                        + Vec2::new(
+                            angle.sin() as f32 * horz_offset,
+                            -angle.cos() as f32 * horz_offset,
+                        ),
+                );
+
+                let trail = missile_shooter.map(|_| missile::gen_trail(&mut commands, &position));
+
+                let mut builder = spawn_bullet(
+                    &mut commands,
+                    &asset_server,
+                    file,
+                    position,
+                    angle,
+                    speed,
+                    Bullet {
                         filter: !bullet_filter.filter,
                         owner: entity,
-                        damage: bullet_shooter.damage,
-                    });
-                    builder.insert_bundle(TransformBundle {
-                        local: transform,
-                        ..default()
-                    });
-                    builder.insert(position);
-                    builder.insert(bullet_rotation);
-                    builder.insert(Velocity(
-                        speed * Vec2::new(angle.cos() as f32, angle.sin() as f32),
+                        damage,
+                        damage_type: bullet_shooter.damage_type,
+                        status_effect: bullet_shooter.status_effect.map(|effect| StatusEffect {
+                            source: Some(entity),
+                            ..effect
+                        }),
+                    },
+                );
+                if 0 < bullet_shooter.pierce {
+                    builder.insert(Pierce(bullet_shooter.pierce));
+                }
+                if 0 < bullet_shooter.ricochet {
+                    builder.insert(Ricochet(bullet_shooter.ricochet));
+                }
+                if 0 < bullet_shooter.pierce || 0 < bullet_shooter.ricochet {
+                    builder.insert(HitTargets::default());
+                }
+                if let Some((target, trail)) = target.zip(trail) {
+                    builder.insert(Missile::new(
+                        target,
+                        trail,
+                        &position,
+                        splash_radius_by_level(tower_level.map(|l| l.level).unwrap_or(0)),
                     ));
-                    builder.insert(StageClear);
-                    if let Some((target, trail)) = target.zip(trail) {
-                        builder.insert(Missile::new(target, trail, &position));
-                    }
-                    builder.add_child(sprite);
-                };
+                }
+            };
 
-            if let Some(rotation) = rotation {
+            if let Some(ref mut pattern) = pattern {
+                let target_position = target
+                    .and_then(|target| target.0)
+                    .and_then(|target| target_query.get(target).ok());
+                // Hold fire if the pattern is aimed but there is no target
+                if let Some(angles) = pattern.volley(position, rotation, target_position, &mut rng)
+                {
+                    for angle in angles {
+                        shoot(pattern.spec.image, angle, pattern.spec.speed, 0., None);
+                    }
+                    sound_writer.send(SoundEvent(Sound::EnemyShot));
+                    bullet_shooter.cooldown += pattern.next_delay(&mut rng);
+                }
+            } else if let Some(rotation) = rotation {
                 if shotgun.is_some() {
                     for i in -3..=3 {
                         shoot(
@@ -155,6 +242,7 @@ pub(crate) fn shoot_bullet(
                             None,
                         );
                     }
+                    sound_writer.send(SoundEvent(Sound::ShotgunShot));
                     bullet_shooter.cooldown += SHOTGUN_SHOOT_INTERVAL;
                 } else if missile_shooter.is_some() {
                     if let Some(target) = target.and_then(|target| target.0) {
@@ -170,41 +258,60 @@ pub(crate) fn shoot_bullet(
                                 Some(target),
                             );
                         }
+                        sound_writer.send(SoundEvent(Sound::MissileLaunch));
                         bullet_shooter.cooldown += MISSILE_SHOOT_INTERVAL;
                     }
                 } else {
-                    shoot(
-                        if bullet_filter.filter {
-                            "agile-enemy-bullet.png"
-                        } else {
-                            "bullet.png"
-                        },
-                        rotation.0,
-                        BULLET_SPEED,
-                        0.,
-                        None,
-                    );
-                    bullet_shooter.cooldown += if bullet_filter.filter {
-                        ENEMY_SHOOT_INTERVAL
-                    } else {
-                        SHOOT_INTERVAL
-                    };
+                    shoot("bullet.png", rotation.0, BULLET_SPEED, 0., None);
+                    sound_writer.send(SoundEvent(Sound::Shot));
+                    bullet_shooter.cooldown += SHOOT_INTERVAL;
                 }
-            } else {
-                shoot(
-                    "enemy-bullet.png",
-                    rand::random::<f64>() * std::f64::consts::PI * 2.,
-                    BULLET_SPEED,
-                    0.,
-                    None,
-                );
-                bullet_shooter.cooldown += SHOOT_INTERVAL * rand::random::<f32>() * 2.;
             }
         }
         bullet_shooter.cooldown -= delta;
     }
 }
 
+/// Spawn a bullet entity flying toward `angle`.
+///
+/// Returns the EntityCommands so that the caller can add more components for special bullets.
+fn spawn_bullet<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    asset_server: &AssetServer,
+    file: &str,
+    position: Position,
+    angle: f64,
+    speed: f32,
+    bullet: Bullet,
+) -> EntityCommands<'w, 's, 'a> {
+    let rotation = Rotation(angle);
+    let mut transform = default();
+    sprite_transform_single(&position, Some(&rotation), &mut transform, 0.);
+    let sprite = commands
+        .spawn_bundle(SpriteBundle {
+            texture: asset_server.load(file),
+            transform: Transform::from_scale(Vec3::ONE * 3.),
+            ..default()
+        })
+        .id();
+
+    let mut builder = commands.spawn();
+    builder
+        .insert(bullet)
+        .insert_bundle(TransformBundle {
+            local: transform,
+            ..default()
+        })
+        .insert(position)
+        .insert(rotation)
+        .insert(Velocity(
+            speed * Vec2::new(angle.cos() as f32, angle.sin() as f32),
+        ))
+        .insert(StageClear)
+        .add_child(sprite);
+    builder
+}
+
 pub(crate) fn bullet_collision_system(
     mut commands: Commands,
     mut target_query: Query<(
@@ -213,38 +320,141 @@ pub(crate) fn bullet_collision_system(
         &mut Health,
         &BulletFilter,
         Option<&Tower>,
+        Option<&Resistances>,
+        Option<&Armor>,
+        Option<&mut Shield>,
+        Option<&mut StatusEffects>,
+    )>,
+    mut bullet_query: Query<(
+        Entity,
+        &Transform,
+        &Bullet,
+        Option<&Missile>,
+        &mut Velocity,
+        &mut Rotation,
+        Option<&mut Pierce>,
+        Option<&mut Ricochet>,
+        Option<&mut HitTargets>,
     )>,
-    bullet_query: Query<(Entity, &Transform, &Bullet, Option<&Missile>)>,
     textures: Res<Textures>,
     mut scoreboard: ResMut<Scoreboard>,
     mut event_writer: EventWriter<GainExpEvent>,
+    mut splash_writer: EventWriter<SplashEvent>,
+    mut text_writer: EventWriter<FloatingTextEvent>,
 ) {
-    for (bullet_entity, bullet_transform, bullet, missile) in bullet_query.iter() {
-        for (entity, transform, health, bullet_filter, tower) in target_query.iter_mut() {
-            if bullet.filter == bullet_filter.filter {
-                single_collision(
-                    &mut commands,
-                    bullet_entity,
-                    bullet,
-                    bullet_transform,
-                    bullet_filter,
-                    missile,
-                    entity,
-                    transform,
-                    tower,
-                    &mut event_writer,
-                    health,
-                    &textures,
-                    &mut scoreboard,
-                );
+    // Ricochets need to know the positions of all targets while we iterate them mutably.
+    // The dead ones waiting for `enemy_death_system` are skipped by the collision anyway.
+    let target_positions: Vec<_> = target_query
+        .iter()
+        .filter(|(_, _, health, ..)| 0. < health.val)
+        .map(|(entity, transform, _, bullet_filter, ..)| {
+            (
+                entity,
+                transform.translation.truncate(),
+                bullet_filter.filter,
+            )
+        })
+        .collect();
+
+    for (
+        bullet_entity,
+        bullet_transform,
+        bullet,
+        missile,
+        mut velocity,
+        mut rotation,
+        mut pierce,
+        mut ricochet,
+        mut hit_targets,
+    ) in bullet_query.iter_mut()
+    {
+        for (
+            entity,
+            transform,
+            health,
+            bullet_filter,
+            tower,
+            resistances,
+            armor,
+            shield,
+            status_effects,
+        ) in target_query.iter_mut()
+        {
+            // A dead enemy lingers until `enemy_death_system` despawns it
+            if bullet.filter != bullet_filter.filter
+                || health.val <= 0.
+                || hit_targets
+                    .as_ref()
+                    .map(|hit_targets| hit_targets.0.contains(&entity))
+                    .unwrap_or(false)
+            {
+                continue;
+            }
+            if !single_collision(
+                &mut commands,
+                bullet,
+                bullet_transform,
+                bullet_filter,
+                missile,
+                entity,
+                transform,
+                tower,
+                &mut event_writer,
+                &mut splash_writer,
+                &mut text_writer,
+                health,
+                (resistances, armor, shield),
+                status_effects,
+                &textures,
+                &mut scoreboard,
+            ) {
+                continue;
             }
+
+            if let Some(hit_targets) = hit_targets.as_mut() {
+                hit_targets.0.push(entity);
+            }
+
+            if let Some(pierce) = pierce.as_mut().filter(|pierce| 0 < pierce.0) {
+                pierce.0 -= 1;
+                continue;
+            }
+
+            if let Some(ricochet) = ricochet.as_mut().filter(|ricochet| 0 < ricochet.0) {
+                let bullet_position = bullet_transform.translation.truncate();
+                let next_target = target_positions
+                    .iter()
+                    .filter(|(target, _, filter)| {
+                        *filter == bullet.filter
+                            && hit_targets
+                                .as_ref()
+                                .map(|hit_targets| !hit_targets.0.contains(target))
+                                .unwrap_or(true)
+                    })
+                    .map(|(_, position, _)| *position)
+                    .min_by(|a, b| {
+                        a.distance_squared(bullet_position)
+                            .partial_cmp(&b.distance_squared(bullet_position))
+                            .unwrap()
+                    });
+                if let Some(next_target) = next_target {
+                    ricochet.0 -= 1;
+                    let delta = next_target - bullet_position;
+                    rotation.0 = delta.y.atan2(delta.x) as f64;
+                    velocity.0 = delta.normalize() * velocity.0.length();
+                    continue;
+                }
+            }
+
+            commands.entity(bullet_entity).despawn_recursive();
+            break;
         }
     }
 }
 
+/// Returns whether the bullet hit the target. The caller decides whether the bullet is consumed.
 fn single_collision(
     commands: &mut Commands,
-    bullet_entity: Entity,
     bullet: &Bullet,
     bullet_transform: &Transform,
     bullet_filter: &BulletFilter,
@@ -253,10 +463,14 @@ fn single_collision(
     transform: &Transform,
     tower: Option<&Tower>,
     event_writer: &mut EventWriter<GainExpEvent>,
+    splash_writer: &mut EventWriter<SplashEvent>,
+    text_writer: &mut EventWriter<FloatingTextEvent>,
     mut health: Mut<Health>,
+    (resistances, armor, shield): (Option<&Resistances>, Option<&Armor>, Option<Mut<Shield>>),
+    status_effects: Option<Mut<StatusEffects>>,
     textures: &Res<Textures>,
     scoreboard: &mut ResMut<Scoreboard>,
-) {
+) -> bool {
     let collision = collide(
         bullet_transform.translation,
         Vec2::new(BULLET_SIZE, BULLET_SIZE),
@@ -264,67 +478,128 @@ fn single_collision(
         Vec2::new(bullet_filter.radius, bullet_filter.radius),
     );
 
-    if collision.is_some() {
-        commands.entity(bullet_entity).despawn_recursive();
-        if let Some(missile) = missile {
-            commands.entity(missile.trail).despawn_recursive();
-        }
-        if health.val < 1. {
-            commands.entity(entity).despawn_recursive();
-            if let Some(tower) = tower {
-                commands.entity(tower.health_bar.0).despawn();
-                commands.entity(tower.health_bar.1).despawn();
-            }
-            commands
-                .spawn_bundle(SpriteSheetBundle {
-                    texture_atlas: textures.large_explosion.clone(),
-                    transform: bullet_transform.clone().with_scale(Vec3::splat(4.0)),
-                    ..default()
-                })
-                .insert(Explosion(Timer::from_seconds(0.15, true)))
-                .insert(StageClear)
-                .insert(TempEnt);
-            scoreboard.score += bullet_filter.exp as f64;
-            scoreboard.credits += bullet_filter.exp as f64;
-
-            event_writer.send(GainExpEvent {
-                entity: bullet.owner,
-                exp: bullet_filter.exp,
-                killed: true,
-            });
-        } else {
-            health.val -= bullet.damage;
-        }
+    if collision.is_none() {
+        return false;
+    }
+
+    if let Some(missile) = missile {
+        commands.entity(missile.trail).despawn_recursive();
+        // Missiles damage everything around the impact instead of the single target
+        splash_writer.send(SplashEvent {
+            position: bullet_transform.translation.truncate(),
+            radius: missile.splash_radius,
+            damage: bullet.damage,
+            damage_type: bullet.damage_type,
+            filter: bullet.filter,
+            owner: bullet.owner,
+        });
+        return true;
+    }
+    let dealt = apply_damage(
+        &mut health,
+        (resistances, armor, shield.map(|shield| shield.into_inner())),
+        bullet.damage,
+        bullet.damage_type,
+        1.,
+    );
+    // Only the damage the player deals is interesting
+    if bullet_filter.filter {
+        text_writer.send(FloatingTextEvent::damage(
+            entity,
+            transform.translation.truncate(),
+            dealt,
+        ));
+    }
+    if let Some((mut status_effects, effect)) = status_effects.zip(bullet.status_effect) {
+        status_effects.apply(effect);
+    }
+    // Checked after the hit, since the collision skips the targets that are already dead
+    if health.val < 1. {
+        destroy_target(
+            commands,
+            entity,
+            &mut health,
+            tower,
+            bullet_transform.translation,
+            bullet_filter,
+            bullet.owner,
+            textures,
+            scoreboard,
+            event_writer,
+        );
+    }
+
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            texture_atlas: textures.small_explosion.clone(),
+            transform: bullet_transform.clone().with_scale(Vec3::splat(3.0)),
+            ..default()
+        })
+        .insert(Explosion(Timer::from_seconds(0.06, true)))
+        .insert(StageClear)
+        .insert(TempEnt);
+
+    true
+}
 
-        commands
-            .spawn_bundle(SpriteSheetBundle {
-                texture_atlas: textures.small_explosion.clone(),
-                transform: bullet_transform.clone().with_scale(Vec3::splat(3.0)),
-                ..default()
-            })
-            .insert(Explosion(Timer::from_seconds(0.06, true)))
-            .insert(StageClear)
-            .insert(TempEnt);
+/// Kill a target that ran out of health and reward the owner.
+///
+/// Towers are despawned right away, while enemies are left at zero health for
+/// `enemy_death_system` so that their death abilities run however they died.
+fn destroy_target(
+    commands: &mut Commands,
+    entity: Entity,
+    health: &mut Health,
+    tower: Option<&Tower>,
+    translation: Vec3,
+    bullet_filter: &BulletFilter,
+    owner: Entity,
+    textures: &Textures,
+    scoreboard: &mut Scoreboard,
+    event_writer: &mut EventWriter<GainExpEvent>,
+) {
+    health.val = 0.;
+    if let Some(tower) = tower {
+        commands.entity(entity).despawn_recursive();
+        commands.entity(tower.health_bar.0).despawn();
+        commands.entity(tower.health_bar.1).despawn();
+        spawn_large_explosion(commands, textures, translation);
+        scoreboard.score += bullet_filter.exp as f64;
+        scoreboard.credits += bullet_filter.exp as f64;
     }
+
+    event_writer.send(GainExpEvent {
+        entity: owner,
+        exp: bullet_filter.exp,
+        killed: true,
+    });
+}
+
+pub(crate) fn spawn_large_explosion(
+    commands: &mut Commands,
+    textures: &Textures,
+    translation: Vec3,
+) {
+    commands
+        .spawn_bundle(SpriteSheetBundle {
+            texture_atlas: textures.large_explosion.clone(),
+            transform: Transform::from_translation(translation).with_scale(Vec3::splat(4.0)),
+            ..default()
+        })
+        .insert(Explosion(Timer::from_seconds(0.15, true)))
+        .insert(StageClear)
+        .insert(TempEnt);
 }
 
 fn cleanup(
     mut commands: Commands,
-    windows: Res<Windows>,
+    arena: Res<ArenaBounds>,
+    current_map: Res<CurrentMap>,
     query: Query<(Entity, &Position, Option<&Missile>), (With<Bullet>, Without<Missile>)>,
 ) {
-    let window = if let Some(window) = windows.iter().next() {
-        window
-    } else {
-        return;
-    };
-    let (width, height) = (window.width(), window.height());
+    let map = current_map.spec();
     for (entity, position, missile) in query.iter() {
-        if position.0.x < -width / 2.
-            || width / 2. < position.0.x
-            || position.0.y < -height / 2.
-            || height / 2. < position.0.y
-        {
+        if !arena.contains(position.0) || map.is_blocked(position.0) {
             commands.entity(entity).despawn_recursive();
             if let Some(missile) = missile {
                 commands.entity(missile.trail).despawn_recursive();
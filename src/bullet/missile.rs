@@ -1,4 +1,12 @@
-use crate::{BulletFilter, Health, Position, Rotation, StageClear, Velocity};
+use super::{destroy_target, GainExpEvent};
+use crate::{
+    damage::{apply_damage, Armor, DamageType, Resistances, Shield},
+    floating_text::FloatingTextEvent,
+    simulation::SimTime,
+    tower::{TempEnt, Tower},
+    BulletFilter, Explosion, Health, Position, Rotation, Scoreboard, StageClear, Textures,
+    Velocity,
+};
 use bevy::{ecs::system::QueryComponentError, prelude::*};
 use bevy_prototype_lyon::prelude::*;
 use std::collections::VecDeque;
@@ -6,6 +14,9 @@ use std::collections::VecDeque;
 const MAX_TIME_TO_LIVE: f32 = 10.;
 const MISSILE_ROTATION_SPEED: f32 = std::f32::consts::PI * 0.5;
 pub(super) const MISSILE_SPEED: f32 = 300.;
+const SPLASH_RADIUS: f32 = 60.;
+/// Fraction of the damage dealt at the edge of the splash radius
+const SPLASH_EDGE_DAMAGE: f32 = 0.25;
 
 #[derive(Component)]
 pub(crate) struct Missile {
@@ -13,10 +24,16 @@ pub(crate) struct Missile {
     pub(super) target: Entity,
     pub(super) trail: Entity,
     pub(super) trail_nodes: VecDeque<Vec2>,
+    pub(super) splash_radius: f32,
 }
 
 impl Missile {
-    pub(super) fn new(target: Entity, trail: Entity, position: &Position) -> Self {
+    pub(super) fn new(
+        target: Entity,
+        trail: Entity,
+        position: &Position,
+        splash_radius: f32,
+    ) -> Self {
         let mut trail_nodes = VecDeque::new();
         trail_nodes.push_back(position.0);
         Self {
@@ -24,13 +41,101 @@ impl Missile {
             target,
             trail,
             trail_nodes,
+            splash_radius,
+        }
+    }
+}
+
+pub(crate) fn splash_radius_by_level(level: usize) -> f32 {
+    SPLASH_RADIUS * (1.1f32).powf(level as f32)
+}
+
+/// Issued when a missile hits something, damaging everything within the radius.
+pub(crate) struct SplashEvent {
+    pub position: Vec2,
+    pub radius: f32,
+    pub damage: f32,
+    pub damage_type: DamageType,
+    /// Same as `Bullet::filter`
+    pub filter: bool,
+    pub owner: Entity,
+}
+
+pub(super) fn splash_damage_system(
+    mut commands: Commands,
+    mut reader: EventReader<SplashEvent>,
+    mut target_query: Query<(
+        Entity,
+        &Position,
+        &mut Health,
+        &BulletFilter,
+        Option<&Tower>,
+        Option<&Resistances>,
+        Option<&Armor>,
+        Option<&mut Shield>,
+    )>,
+    textures: Res<Textures>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut event_writer: EventWriter<GainExpEvent>,
+    mut text_writer: EventWriter<FloatingTextEvent>,
+) {
+    for event in reader.iter() {
+        commands
+            .spawn_bundle(SpriteSheetBundle {
+                texture_atlas: textures.large_explosion.clone(),
+                transform: Transform::from_xyz(event.position.x, event.position.y, 0.2)
+                    // The atlas has 32 pixel sprites
+                    .with_scale(Vec3::splat(event.radius * 2. / 32.)),
+                ..default()
+            })
+            .insert(Explosion(Timer::from_seconds(0.1, true)))
+            .insert(StageClear)
+            .insert(TempEnt);
+
+        for (entity, position, mut health, bullet_filter, tower, resistances, armor, mut shield) in
+            target_query.iter_mut()
+        {
+            // Multiple splashes can hit the same target in a frame, but it should be destroyed only
+            // once
+            if event.filter != bullet_filter.filter || health.val <= 0. {
+                continue;
+            }
+            let dist = (position.0.distance(event.position) - bullet_filter.radius).max(0.);
+            if event.radius < dist {
+                continue;
+            }
+            let falloff = 1. - (1. - SPLASH_EDGE_DAMAGE) * dist / event.radius;
+            let dealt = apply_damage(
+                &mut health,
+                (resistances, armor, shield.as_deref_mut()),
+                event.damage * falloff,
+                event.damage_type,
+                1.,
+            );
+            if bullet_filter.filter {
+                text_writer.send(FloatingTextEvent::damage(entity, position.0, dealt));
+            }
+            if health.val < 1. {
+                destroy_target(
+                    &mut commands,
+                    entity,
+                    &mut health,
+                    tower,
+                    position.0.extend(0.2),
+                    bullet_filter,
+                    event.owner,
+                    &textures,
+                    &mut scoreboard,
+                    &mut event_writer,
+                );
+            }
         }
     }
 }
 
 pub(super) fn missile_system(
     mut commands: Commands,
-    time: Res<Time>,
+    time: Res<SimTime>,
     mut query: Query<(
         Entity,
         &mut Missile,
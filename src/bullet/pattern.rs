@@ -0,0 +1,153 @@
+use crate::{simulation::GameRng, Position, Rotation};
+use bevy::prelude::*;
+use rand::Rng;
+use std::f64::consts::PI;
+
+/// Shapes of a volley of bullets
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum PatternKind {
+    /// `count` bullets evenly spaced around the shooter
+    Ring { count: usize },
+    /// `count` bullets fanned out over `spread` radians around the direction the shooter is
+    /// facing, or a random direction if it has no rotation
+    Spread { count: usize, spread: f64 },
+    /// `arms` bullets evenly spaced around the shooter, rotating by `angular_velocity` radians
+    /// per second
+    Spiral { arms: usize, angular_velocity: f64 },
+    /// `count` bullets aimed at the target, fanned out over `spread` radians
+    Aimed { count: usize, spread: f64 },
+}
+
+/// Description of a bullet pattern that can be authored as constant data
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PatternSpec {
+    pub kind: PatternKind,
+    /// Seconds between bursts
+    pub interval: f32,
+    /// Random variation of `interval` as a fraction of it, 0 for a steady rate
+    pub jitter: f32,
+    /// Number of volleys in a burst
+    pub burst: usize,
+    /// Seconds between volleys in a burst
+    pub burst_delay: f32,
+    pub speed: f32,
+    pub image: &'static str,
+}
+
+impl PatternSpec {
+    /// A pattern firing a single volley every `interval` seconds
+    pub(crate) const fn new(
+        kind: PatternKind,
+        interval: f32,
+        speed: f32,
+        image: &'static str,
+    ) -> Self {
+        Self {
+            kind,
+            interval,
+            jitter: 0.,
+            burst: 1,
+            burst_delay: 0.,
+            speed,
+            image,
+        }
+    }
+
+    pub(crate) const fn with_jitter(mut self, jitter: f32) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub(crate) const fn with_burst(mut self, burst: usize, burst_delay: f32) -> Self {
+        self.burst = burst;
+        self.burst_delay = burst_delay;
+        self
+    }
+}
+
+/// Makes the `BulletShooter` fire in a pattern instead of the tower weapons.
+#[derive(Component)]
+pub(crate) struct BulletPattern {
+    pub(super) spec: PatternSpec,
+    /// Current angle of a spiral
+    angle: f64,
+    /// Volleys left in the current burst
+    burst_left: usize,
+}
+
+impl BulletPattern {
+    pub(crate) fn new(spec: PatternSpec) -> Self {
+        Self {
+            spec,
+            angle: 0.,
+            burst_left: spec.burst,
+        }
+    }
+
+    /// Switch to another pattern, starting a new burst
+    pub(crate) fn set_spec(&mut self, spec: PatternSpec) {
+        self.spec = spec;
+        self.burst_left = spec.burst;
+    }
+
+    pub(super) fn update(&mut self, delta: f32) {
+        if let PatternKind::Spiral {
+            angular_velocity, ..
+        } = self.spec.kind
+        {
+            self.angle = (self.angle + angular_velocity * delta as f64) % (PI * 2.);
+        }
+    }
+
+    /// Angles of the bullets in the next volley, or None if the pattern needs a target
+    /// that doesn't exist.
+    pub(super) fn volley(
+        &self,
+        position: &Position,
+        rotation: Option<&Rotation>,
+        target_position: Option<&Position>,
+        rng: &mut GameRng,
+    ) -> Option<Vec<f64>> {
+        let fan = |center: f64, count: usize, spread: f64| {
+            (0..count)
+                .map(|i| {
+                    if count <= 1 {
+                        center
+                    } else {
+                        center - spread / 2. + spread * i as f64 / (count - 1) as f64
+                    }
+                })
+                .collect()
+        };
+
+        Some(match self.spec.kind {
+            PatternKind::Ring { count } => (0..count)
+                .map(|i| i as f64 * PI * 2. / count as f64)
+                .collect(),
+            PatternKind::Spread { count, spread } => {
+                let center = rotation
+                    .map(|rotation| rotation.0)
+                    .unwrap_or_else(|| rng.gen::<f64>() * PI * 2.);
+                fan(center, count, spread)
+            }
+            PatternKind::Spiral { arms, .. } => (0..arms)
+                .map(|i| self.angle + i as f64 * PI * 2. / arms as f64)
+                .collect(),
+            PatternKind::Aimed { count, spread } => {
+                let delta = target_position?.0 - position.0;
+                fan(delta.y.atan2(delta.x) as f64, count, spread)
+            }
+        })
+    }
+
+    /// Advance the burst after a volley and return the cooldown until the next one
+    pub(super) fn next_delay(&mut self, rng: &mut GameRng) -> f32 {
+        if 1 < self.burst_left {
+            self.burst_left -= 1;
+            self.spec.burst_delay
+        } else {
+            self.burst_left = self.spec.burst;
+            self.spec.interval * (1. + self.spec.jitter * (rng.gen::<f32>() * 2. - 1.))
+        }
+    }
+}
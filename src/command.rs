@@ -0,0 +1,296 @@
+//! Every change the player makes to a stage goes through a `PlayerCommand`.
+//!
+//! The mouse, the buttons and the replay only send commands, and `apply_player_commands`
+//! checks them against the current state before applying, so that the rules such as the
+//! credits and the tower overlap live in one place.
+
+use crate::{
+    arena::ArenaBounds,
+    bullet::BulletShooter,
+    map::CurrentMap,
+    replay::{ReplayCommand, ReplayEvent},
+    sound::{Sound, SoundEvent},
+    tower::{AimMode, BeamTower, Targeting, Tower},
+    ui::{PauseEvent, QuitEvent, TowerPalette},
+    undo::{undo_window_open, UndoEntry, UndoStack},
+    Level, Position, Scoreboard,
+};
+use bevy::prelude::*;
+
+pub(crate) struct CommandPlugin;
+
+impl Plugin for CommandPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerCommand>();
+        app.add_system(apply_player_commands);
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum PlayerCommand {
+    /// Buy a tower and place it at the position
+    PlaceTower {
+        tower: TowerPalette,
+        position: Vec2,
+    },
+    MoveTower {
+        tower: Entity,
+        position: Vec2,
+    },
+    /// Remove a tower. The trashcan doesn't refund the credits.
+    SellTower {
+        tower: Entity,
+    },
+    Pause,
+    Quit,
+    SetTargeting {
+        tower: Entity,
+        targeting: Targeting,
+    },
+    /// Only one tower at a time is aimed manually
+    SetAimMode {
+        tower: Entity,
+        mode: AimMode,
+    },
+    /// Take back the last placement or move while the stage is being set up
+    Undo,
+    /// End the build phase
+    StartWave,
+}
+
+/// Why a tower can't be at the position, if it can't.
+///
+/// `towers` are the positions and sizes of the other towers.
+pub(crate) fn placement_error(
+    arena: &ArenaBounds,
+    current_map: &CurrentMap,
+    mut towers: impl Iterator<Item = (Vec2, f32)>,
+    position: Vec2,
+    size: f32,
+) -> Option<&'static str> {
+    if !arena.contains(position) {
+        Some("Outside the arena")
+    } else if !current_map.spec().is_buildable(position, size) {
+        Some("Not buildable")
+    } else if towers
+        .any(|(other, other_size)| position.distance_squared(other) < (size + other_size).powf(2.))
+    {
+        Some("Overlaps another tower")
+    } else {
+        None
+    }
+}
+
+pub(crate) fn apply_player_commands(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    arena: Res<ArenaBounds>,
+    current_map: Res<CurrentMap>,
+    mut level: ResMut<Level>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut undo_stack: ResMut<UndoStack>,
+    mut query_towers: Query<(Entity, &mut Position, &Tower, &mut Targeting)>,
+    mut query_aim: Query<
+        (
+            Entity,
+            &mut AimMode,
+            Option<&BulletShooter>,
+            Option<&BeamTower>,
+        ),
+        With<Tower>,
+    >,
+    mut reader: EventReader<PlayerCommand>,
+    mut pause_writer: EventWriter<PauseEvent>,
+    mut quit_writer: EventWriter<QuitEvent>,
+    mut sound_writer: EventWriter<SoundEvent>,
+    mut replay_writer: EventWriter<ReplayEvent>,
+) {
+    // Spawned and despawned towers don't show up in the query until the next frame
+    let mut placed: Vec<(Vec2, f32)> = vec![];
+    let mut removed: Vec<Entity> = vec![];
+
+    for command in reader.iter() {
+        match *command {
+            PlayerCommand::PlaceTower { tower, position } => {
+                // Towers are only bought for a stage, but can be rearranged between stages
+                if !level.can_build() {
+                    continue;
+                }
+                let tower_count = query_towers.iter().count() + placed.len() - removed.len();
+                let cost = tower.cost(tower_count);
+                if scoreboard.credits < cost {
+                    println!("Cannot place {tower:?}: not enough credits");
+                    continue;
+                }
+                let others = query_towers
+                    .iter()
+                    .filter(|(entity, ..)| !removed.contains(entity))
+                    .map(|(_, position, tower, _)| (position.0, tower.size))
+                    .chain(placed.iter().copied());
+                if let Some(error) =
+                    placement_error(&arena, &current_map, others, position, tower.size())
+                {
+                    println!("Cannot place {tower:?}: {error}");
+                    continue;
+                }
+                let entity = tower.spawn(&mut commands, &asset_server, position);
+                scoreboard.credits -= cost;
+                if undo_window_open(&level) {
+                    undo_stack.0.push(UndoEntry::Placed {
+                        tower: entity,
+                        cost,
+                    });
+                }
+                placed.push((position, tower.size()));
+                sound_writer.send(SoundEvent(Sound::Place));
+                replay_writer.send(ReplayEvent(ReplayCommand::Purchase { tower, position }));
+            }
+            PlayerCommand::MoveTower { tower, position } => {
+                let (from, size) = if let Ok((_, from, tower, _)) = query_towers.get(tower) {
+                    (from.0, tower.size)
+                } else {
+                    continue;
+                };
+                if from == position {
+                    continue;
+                }
+                let others = query_towers
+                    .iter()
+                    .filter(|(entity, ..)| *entity != tower && !removed.contains(entity))
+                    .map(|(_, position, tower, _)| (position.0, tower.size))
+                    .chain(placed.iter().copied());
+                if let Some(error) = placement_error(&arena, &current_map, others, position, size) {
+                    println!("Cannot move the tower: {error}");
+                    continue;
+                }
+                if let Ok((_, mut tower_position, ..)) = query_towers.get_mut(tower) {
+                    tower_position.0 = position;
+                }
+                if undo_window_open(&level) {
+                    undo_stack.0.push(UndoEntry::Moved { tower, from });
+                }
+                sound_writer.send(SoundEvent(Sound::Place));
+                replay_writer.send(ReplayEvent(ReplayCommand::Move { from, to: position }));
+            }
+            PlayerCommand::SellTower { tower: entity } => {
+                if removed.contains(&entity) {
+                    continue;
+                }
+                if let Ok((_, position, tower, _)) = query_towers.get(entity) {
+                    despawn_tower(&mut commands, entity, tower);
+                    removed.push(entity);
+                    sound_writer.send(SoundEvent(Sound::Trash));
+                    replay_writer.send(ReplayEvent(ReplayCommand::Trash {
+                        position: position.0,
+                    }));
+                }
+            }
+            PlayerCommand::Pause => {
+                pause_writer.send(PauseEvent);
+                replay_writer.send(ReplayEvent(ReplayCommand::Pause));
+            }
+            PlayerCommand::Quit => {
+                quit_writer.send(QuitEvent);
+                replay_writer.send(ReplayEvent(ReplayCommand::Quit));
+            }
+            PlayerCommand::SetTargeting { tower, targeting } => {
+                if let Ok((_, position, _, mut tower_targeting)) = query_towers.get_mut(tower) {
+                    if *tower_targeting != targeting {
+                        *tower_targeting = targeting;
+                        replay_writer.send(ReplayEvent(ReplayCommand::SetTargeting {
+                            position: position.0,
+                            targeting,
+                        }));
+                    }
+                }
+            }
+            PlayerCommand::SetAimMode { tower, mode } => {
+                // Only the shooting towers have anything to aim
+                match query_aim.get(tower) {
+                    Ok((_, aim_mode, bullet_shooter, beam_tower))
+                        if *aim_mode != mode
+                            && (bullet_shooter.is_some() || beam_tower.is_some()) => {}
+                    _ => continue,
+                }
+                if mode == AimMode::Manual {
+                    for (entity, mut aim_mode, ..) in query_aim.iter_mut() {
+                        if entity != tower && *aim_mode == AimMode::Manual {
+                            *aim_mode = AimMode::Auto;
+                        }
+                    }
+                }
+                if let Ok((_, mut aim_mode, ..)) = query_aim.get_mut(tower) {
+                    *aim_mode = mode;
+                }
+                if let Ok((_, position, ..)) = query_towers.get(tower) {
+                    replay_writer.send(ReplayEvent(ReplayCommand::SetAimMode {
+                        position: position.0,
+                        mode,
+                    }));
+                }
+            }
+            PlayerCommand::StartWave => {
+                if let Level::Build { difficulty, .. } = *level {
+                    *level = Level::start(difficulty);
+                }
+            }
+            PlayerCommand::Undo => {
+                if !undo_window_open(&level) {
+                    continue;
+                }
+                // Entries of towers that have been trashed since are skipped
+                while let Some(entry) = undo_stack.0.pop() {
+                    let consumed = match entry {
+                        UndoEntry::Placed {
+                            tower: entity,
+                            cost,
+                        } => match query_towers.get(entity) {
+                            Ok((_, _, tower, _)) if !removed.contains(&entity) => {
+                                despawn_tower(&mut commands, entity, tower);
+                                removed.push(entity);
+                                scoreboard.credits += cost;
+                                true
+                            }
+                            _ => false,
+                        },
+                        UndoEntry::Moved { tower, from } => {
+                            let size = if let Ok((_, _, tower, _)) = query_towers.get(tower) {
+                                tower.size
+                            } else {
+                                continue;
+                            };
+                            // Another tower may have taken the old spot since. The entry is
+                            // used up either way, so that the replay undoes the same entries.
+                            let others = query_towers
+                                .iter()
+                                .filter(|(entity, ..)| {
+                                    *entity != tower && !removed.contains(entity)
+                                })
+                                .map(|(_, position, tower, _)| (position.0, tower.size))
+                                .chain(placed.iter().copied());
+                            if let Some(error) =
+                                placement_error(&arena, &current_map, others, from, size)
+                            {
+                                println!("Cannot undo the move: {error}");
+                            } else if let Ok((_, mut position, ..)) = query_towers.get_mut(tower) {
+                                position.0 = from;
+                            }
+                            true
+                        }
+                    };
+                    if consumed {
+                        replay_writer.send(ReplayEvent(ReplayCommand::Undo));
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Despawn a tower along with its health bar
+pub(crate) fn despawn_tower(commands: &mut Commands, entity: Entity, tower: &Tower) {
+    commands.entity(tower.health_bar.0).despawn();
+    commands.entity(tower.health_bar.1).despawn();
+    commands.entity(entity).despawn_recursive();
+}
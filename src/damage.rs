@@ -0,0 +1,178 @@
+use crate::{
+    simulation::{SimTime, SimulationStage},
+    Health,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::{entity::ShapeBundle, prelude::*, shapes::Circle};
+
+pub(crate) struct DamagePlugin;
+
+impl Plugin for DamagePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(SimulationStage, shield_regen_system);
+        app.add_system(shield_ring_system);
+    }
+}
+
+/// Armor never reduces a hit below this fraction of the original damage
+const MIN_DAMAGE_RATIO: f32 = 0.1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum DamageType {
+    /// Bullets and shotgun pellets
+    Kinetic,
+    /// Missiles
+    Explosive,
+    /// Beams
+    Energy,
+}
+
+impl std::fmt::Display for DamageType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Kinetic => write!(f, "Kinetic"),
+            Self::Explosive => write!(f, "Explosive"),
+            Self::Energy => write!(f, "Energy"),
+        }
+    }
+}
+
+/// Damage multipliers for each `DamageType`.
+///
+/// A value below 1 is a resistance and above 1 is a weakness.
+#[derive(Component, Clone, Copy, Debug)]
+pub(crate) struct Resistances {
+    pub kinetic: f32,
+    pub explosive: f32,
+    pub energy: f32,
+}
+
+impl Resistances {
+    pub(crate) const NEUTRAL: Self = Self::new(1., 1., 1.);
+
+    pub(crate) const fn new(kinetic: f32, explosive: f32, energy: f32) -> Self {
+        Self {
+            kinetic,
+            explosive,
+            energy,
+        }
+    }
+
+    pub(crate) fn multiplier(&self, damage_type: DamageType) -> f32 {
+        match damage_type {
+            DamageType::Kinetic => self.kinetic,
+            DamageType::Explosive => self.explosive,
+            DamageType::Energy => self.energy,
+        }
+    }
+}
+
+/// Flat damage reduction applied to every hit.
+#[derive(Component)]
+pub(crate) struct Armor(pub f32);
+
+/// A regenerating pool of hit points that absorbs damage before `Health`.
+#[derive(Component)]
+pub(crate) struct Shield {
+    pub val: f32,
+    pub max: f32,
+    /// Regeneration amount per second
+    pub regen: f32,
+    /// Seconds left until the shield starts regenerating after being hit
+    pub regen_delay: f32,
+    pub ring: Entity,
+}
+
+const SHIELD_REGEN_DELAY: f32 = 3.;
+const SHIELD_RING_COLOR: Color = Color::rgba(0.4, 0.7, 1.0, 1.0);
+
+impl Shield {
+    pub(crate) fn new(max: f32, ring: Entity) -> Self {
+        Self {
+            val: max,
+            max,
+            regen: max / 10.,
+            regen_delay: 0.,
+            ring,
+        }
+    }
+}
+
+/// Apply a single hit of `damage` to the target, scaling it by the resistance to the damage
+/// type, reducing it by armor and then letting the shield absorb it.
+/// Returns the damage actually dealt to health.
+///
+/// Continuous damage like the beam should pass `delta` as the duration of the hit, so that
+/// armor is applied per second instead of per frame. Instantaneous hits pass 1.
+pub(crate) fn apply_damage(
+    health: &mut Health,
+    defense: (Option<&Resistances>, Option<&Armor>, Option<&mut Shield>),
+    damage: f32,
+    damage_type: DamageType,
+    delta: f32,
+) -> f32 {
+    let (resistances, armor, shield) = defense;
+    let damage = damage
+        * resistances
+            .map(|resistances| resistances.multiplier(damage_type))
+            .unwrap_or(1.);
+
+    let mut damage = if let Some(armor) = armor {
+        (damage - armor.0 * delta).max(damage * MIN_DAMAGE_RATIO)
+    } else {
+        damage
+    };
+
+    if let Some(shield) = shield {
+        let absorbed = damage.min(shield.val);
+        shield.val -= absorbed;
+        shield.regen_delay = SHIELD_REGEN_DELAY;
+        damage -= absorbed;
+    }
+
+    let dealt = damage.min(health.val.max(0.));
+    health.val -= damage;
+    dealt
+}
+
+pub(crate) fn shield_ring(radius: f32) -> ShapeBundle {
+    let shape = Circle {
+        radius,
+        center: Vec2::ZERO,
+    };
+
+    GeometryBuilder::build_as(
+        &shape,
+        DrawMode::Stroke(StrokeMode::new(SHIELD_RING_COLOR, 3.0)),
+        Transform::from_xyz(0., 0., 0.06),
+    )
+}
+
+fn shield_regen_system(time: Res<SimTime>, mut query: Query<&mut Shield>) {
+    let delta = time.delta_seconds();
+    for mut shield in query.iter_mut() {
+        if shield.regen_delay < delta {
+            shield.regen_delay = 0.;
+            shield.val = (shield.val + shield.regen * delta).min(shield.max);
+        } else {
+            shield.regen_delay -= delta;
+        }
+    }
+}
+
+/// Fade the shield ring according to the remaining shield.
+fn shield_ring_system(
+    query: Query<&Shield, Changed<Shield>>,
+    mut query_ring: Query<(&mut DrawMode, &mut Visibility)>,
+) {
+    for shield in query.iter() {
+        if let Ok((mut draw_mode, mut visibility)) = query_ring.get_mut(shield.ring) {
+            let factor = shield.val / shield.max;
+            visibility.is_visible = 0. < factor;
+            *draw_mode = DrawMode::Stroke(StrokeMode::new(
+                *SHIELD_RING_COLOR.clone().set_a(factor),
+                1. + 2. * factor,
+            ));
+        }
+    }
+}
@@ -1,20 +1,51 @@
+mod ability;
+mod boss;
+mod flocking;
+mod formation;
+
 use crate::{
-    bullet::{BulletShooter, ENEMY_SIZE},
-    can_update, sprite_transform_single,
+    arena::ArenaBounds,
+    bullet::{
+        spawn_large_explosion, BulletPattern, BulletShooter, PatternKind, PatternSpec,
+        BULLET_SPEED, ENEMY_SIZE, SHOOT_INTERVAL,
+    },
+    damage::{shield_ring, Armor, DamageType, Resistances, Shield},
+    floating_text::FloatingTextEvent,
+    map::CurrentMap,
+    simulation::{GameRng, SimTime, SimulationStage},
+    sprite_transform_single,
+    status_effect::{speed_factor, BaseTint, StatusEffects},
     tower::{apprach_angle, MissileShooter, Tower},
-    BulletFilter, Health, Level, Position, Rotation, StageClear, Target, Velocity,
+    BulletFilter, Health, Level, Position, Rotation, Scoreboard, StageClear, Target, Textures,
+    Velocity,
 };
 use bevy::{ecs::system::EntityCommands, prelude::*};
+use rand::Rng;
+
+use self::ability::{carrier_system, medic_system, split, EnemyAbility, Splitter};
+pub(crate) use self::boss::Boss;
+use self::boss::{boss_system, spawn_boss_system};
+use self::flocking::{flocking_system, Flocking};
+use self::formation::{
+    formation_system, spawn_formation, FormationShape, FormationSlot, FormationSpec,
+};
 
 pub(crate) struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
+        app.add_system_set_to_stage(
+            SimulationStage,
             SystemSet::new()
-                .with_run_criteria(can_update)
                 .with_system(spawn_enemies)
+                .with_system(spawn_boss_system)
+                .with_system(boss_system)
                 .with_system(enemy_system)
+                .with_system(formation_system)
+                .with_system(flocking_system)
+                .with_system(carrier_system)
+                .with_system(medic_system)
+                .with_system(enemy_death_system)
                 .with_system(agile_enemy_system)
                 .with_system(sturdy_enemy_system)
                 .with_system(missile_enemy_system),
@@ -32,16 +63,35 @@ struct AgileEnemy(bool);
 struct SturdyEnemy;
 
 const MAX_ENEMIES: usize = 100;
+/// Chance that a spawn of an enemy with formations is a whole formation
+const FORMATION_CHANCE: f32 = 0.15;
 
 struct EnemySpec {
     #[allow(dead_code)]
     waves: usize,
+    /// The lowest stage difficulty this enemy appears in
+    min_difficulty: usize,
     image: &'static str,
+    /// Tint of the sprite to tell apart enemies sharing an image
+    tint: Color,
     health: f32,
     size: f32,
     sprite_scale: f32,
     exp: usize,
     bullet_damage: f32,
+    bullet_damage_type: DamageType,
+    /// Damage multipliers against each damage type of the towers
+    resistances: Resistances,
+    /// Flat damage reduction per hit, 0 for no armor
+    armor: f32,
+    /// Capacity of the regenerating shield, 0 for no shield
+    shield: f32,
+    /// How the enemy fires, or None for enemies with their own weapon
+    pattern: Option<PatternSpec>,
+    /// Formations this enemy can spawn in. Only for enemies that don't steer on their own.
+    formations: &'static [FormationSpec],
+    flocking: Flocking,
+    abilities: &'static [EnemyAbility],
     more_components: fn(&mut EntityCommands),
     freq: fn(f32) -> f32,
 }
@@ -76,51 +126,130 @@ struct EnemySpec {
 //     }
 // }
 
-const ENEMY_SPECS: [EnemySpec; 5] = [
-    EnemySpec {
-        waves: 0,
-        image: "enemy.png",
-        health: 10.,
-        size: ENEMY_SIZE,
-        sprite_scale: 3.,
-        exp: 10,
-        bullet_damage: 1.,
-        more_components: |_| (),
-        freq: |f| {
-            if f < 20. {
-                (f + 2.) / 20.
-            } else {
-                1. / (f - 20. + 1.)
-            }
+const BASIC_ENEMY: EnemySpec = EnemySpec {
+    waves: 0,
+    min_difficulty: 0,
+    image: "enemy.png",
+    tint: Color::WHITE,
+    health: 10.,
+    size: ENEMY_SIZE,
+    sprite_scale: 3.,
+    exp: 10,
+    bullet_damage: 1.,
+    bullet_damage_type: DamageType::Kinetic,
+    resistances: Resistances::NEUTRAL,
+    armor: 0.,
+    shield: 0.,
+    pattern: Some(
+        PatternSpec::new(
+            PatternKind::Spread {
+                count: 1,
+                spread: 0.,
+            },
+            SHOOT_INTERVAL,
+            BULLET_SPEED,
+            "enemy-bullet.png",
+        )
+        .with_jitter(1.),
+    ),
+    formations: &[
+        FormationSpec {
+            shape: FormationShape::V,
+            count: 5,
+            spacing: 40.,
+            speed: 60.,
         },
-        // ..EnemySpec::default()
-    },
-    EnemySpec {
-        waves: 5,
-        image: "boss.png",
-        health: 150.,
-        size: ENEMY_SIZE * 2.,
-        sprite_scale: 3.,
-        exp: 150,
-        bullet_damage: 1.,
-        more_components: |_| (),
-        freq: |f| {
-            if f < 40. {
-                (f * 5000. + 10000.) / 10000000.
-            } else {
-                0.001 / (f - 40. + 1.)
-            }
+        FormationSpec {
+            shape: FormationShape::Line,
+            count: 5,
+            spacing: 40.,
+            speed: 40.,
         },
-        // ..EnemySpec::default()
+        FormationSpec {
+            shape: FormationShape::Circle,
+            count: 8,
+            spacing: 40.,
+            speed: 30.,
+        },
+    ],
+    flocking: Flocking::new(40., 100., 0.5),
+    abilities: &[],
+    more_components: |_| (),
+    freq: |f| {
+        if f < 20. {
+            (f + 2.) / 20.
+        } else {
+            1. / (f - 20. + 1.)
+        }
     },
+    // ..EnemySpec::default()
+};
+
+/// Spawned by splitters on death
+const SPLITLING: EnemySpec = EnemySpec {
+    waves: 0,
+    min_difficulty: 0,
+    image: "enemy.png",
+    tint: Color::rgb(1., 0.6, 0.3),
+    health: 5.,
+    size: ENEMY_SIZE * 0.7,
+    sprite_scale: 2.,
+    exp: 5,
+    bullet_damage: 1.,
+    bullet_damage_type: DamageType::Kinetic,
+    resistances: Resistances::NEUTRAL,
+    armor: 0.,
+    shield: 0.,
+    pattern: Some(
+        PatternSpec::new(
+            PatternKind::Spread {
+                count: 1,
+                spread: 0.,
+            },
+            SHOOT_INTERVAL * 2.,
+            BULLET_SPEED,
+            "enemy-bullet.png",
+        )
+        .with_jitter(1.),
+    ),
+    formations: &[],
+    flocking: Flocking::new(30., 100., 0.5),
+    abilities: &[],
+    more_components: |_| (),
+    freq: |_| 0.,
+};
+
+const ENEMY_SPECS: [EnemySpec; 7] = [
+    BASIC_ENEMY,
     EnemySpec {
         waves: 10,
+        min_difficulty: 2,
         image: "enemy3.png",
+        tint: Color::WHITE,
         health: 50.,
         size: ENEMY_SIZE * 1.2,
         sprite_scale: 3.,
         exp: 50,
         bullet_damage: 1.,
+        bullet_damage_type: DamageType::Kinetic,
+        resistances: Resistances::new(1.25, 0.5, 1.),
+        armor: 0.,
+        shield: 0.,
+        pattern: Some(
+            PatternSpec::new(
+                PatternKind::Spread {
+                    count: 1,
+                    spread: 0.,
+                },
+                1.3,
+                BULLET_SPEED,
+                "agile-enemy-bullet.png",
+            )
+            .with_burst(3, 0.1),
+        ),
+        formations: &[],
+        flocking: Flocking::new(50., 150., 0.),
+        abilities: &[],
         more_components: |builder| {
             builder.insert(Rotation(0.));
             builder.insert(Target(None));
@@ -130,12 +259,30 @@ const ENEMY_SPECS: [EnemySpec; 5] = [
     },
     EnemySpec {
         waves: 20,
+        min_difficulty: 3,
         image: "enemy4.png",
+        tint: Color::WHITE,
         health: 500.,
         size: ENEMY_SIZE * 1.5,
         sprite_scale: 3.,
         exp: 500,
         bullet_damage: 1.,
+        bullet_damage_type: DamageType::Kinetic,
+        resistances: Resistances::new(0.75, 1., 1.5),
+        armor: 0.5,
+        shield: 0.,
+        pattern: Some(PatternSpec::new(
+            PatternKind::Spread {
+                count: 3,
+                spread: 0.3,
+            },
+            1.5,
+            BULLET_SPEED,
+            "agile-enemy-bullet.png",
+        )),
+        formations: &[],
+        flocking: Flocking::new(60., 60., 0.),
+        abilities: &[],
         more_components: |builder| {
             builder.insert(Rotation(0.));
             builder.insert(Target(None));
@@ -145,12 +292,22 @@ const ENEMY_SPECS: [EnemySpec; 5] = [
     },
     EnemySpec {
         waves: 40,
+        min_difficulty: 4,
         image: "missile-enemy.png",
+        tint: Color::WHITE,
         health: 3500.,
         size: ENEMY_SIZE * 1.5,
         sprite_scale: 2.,
         exp: 3500,
         bullet_damage: 3.,
+        bullet_damage_type: DamageType::Explosive,
+        resistances: Resistances::new(1., 0.5, 1.25),
+        armor: 2.,
+        shield: 300.,
+        pattern: None,
+        formations: &[],
+        flocking: Flocking::new(60., 60., 0.),
+        abilities: &[],
         more_components: |builder| {
             builder.insert(Rotation(0.));
             builder.insert(Target(None));
@@ -158,15 +315,102 @@ const ENEMY_SPECS: [EnemySpec; 5] = [
         },
         freq: |f| (f * 5000. + 10000.) / 20000000.,
     },
+    EnemySpec {
+        waves: 0,
+        min_difficulty: 1,
+        image: "enemy.png",
+        tint: Color::rgb(1., 0.6, 0.3),
+        health: 80.,
+        size: ENEMY_SIZE * 1.4,
+        sprite_scale: 4.5,
+        exp: 40,
+        bullet_damage: 1.,
+        bullet_damage_type: DamageType::Kinetic,
+        resistances: Resistances::NEUTRAL,
+        armor: 0.,
+        shield: 0.,
+        pattern: Some(
+            PatternSpec::new(
+                PatternKind::Spread {
+                    count: 1,
+                    spread: 0.,
+                },
+                SHOOT_INTERVAL * 2.,
+                BULLET_SPEED,
+                "enemy-bullet.png",
+            )
+            .with_jitter(1.),
+        ),
+        formations: &[],
+        flocking: Flocking::new(50., 100., 0.5),
+        abilities: &[EnemyAbility::Split {
+            into: &SPLITLING,
+            count: 4,
+        }],
+        more_components: |_| (),
+        freq: |f| (f * 5000. + 10000.) / 10000000.,
+    },
+    EnemySpec {
+        waves: 0,
+        min_difficulty: 1,
+        image: "enemy.png",
+        tint: Color::rgb(0.4, 1., 0.5),
+        health: 60.,
+        size: ENEMY_SIZE * 1.2,
+        sprite_scale: 3.5,
+        exp: 60,
+        bullet_damage: 1.,
+        bullet_damage_type: DamageType::Kinetic,
+        resistances: Resistances::new(1., 1., 0.75),
+        armor: 0.,
+        shield: 0.,
+        pattern: None,
+        formations: &[],
+        flocking: Flocking::new(50., 100., 0.5),
+        abilities: &[EnemyAbility::Heal {
+            range: 250.,
+            amount: 5.,
+            interval: 1.,
+        }],
+        more_components: |_| (),
+        freq: |f| (f * 5000. + 10000.) / 20000000.,
+    },
+    EnemySpec {
+        waves: 0,
+        min_difficulty: 2,
+        image: "enemy4.png",
+        tint: Color::rgb(0.8, 0.5, 1.),
+        health: 300.,
+        size: ENEMY_SIZE * 1.8,
+        sprite_scale: 3.5,
+        exp: 200,
+        bullet_damage: 1.,
+        bullet_damage_type: DamageType::Kinetic,
+        resistances: Resistances::new(0.75, 1.25, 1.),
+        armor: 1.,
+        shield: 0.,
+        pattern: None,
+        formations: &[],
+        flocking: Flocking::new(70., 60., 0.),
+        abilities: &[EnemyAbility::Carry {
+            minion: &BASIC_ENEMY,
+            count: 3,
+            interval: 6.,
+        }],
+        more_components: |_| (),
+        freq: |f| (f * 5000. + 10000.) / 20000000.,
+    },
 ];
 
 fn spawn_enemies(
     mut commands: Commands,
     query: Query<&Enemy>,
     asset_server: Res<AssetServer>,
-    windows: Res<Windows>,
-    time: Res<Time>,
+    current_map: Res<CurrentMap>,
+    arena: Res<ArenaBounds>,
+    time: Res<SimTime>,
     level: Res<Level>,
+    mut rng: ResMut<GameRng>,
 ) {
     let enemy_count = query.iter().count();
     if MAX_ENEMIES <= enemy_count {
@@ -178,88 +422,169 @@ fn spawn_enemies(
         return;
     };
 
-    let window = if let Some(window) = windows.iter().next() {
-        window
-    } else {
-        return;
-    };
-    let (width, height) = (window.width(), window.height());
+    let map = current_map.spec();
 
-    for enemy_spec in ENEMY_SPECS.iter().take(*difficulty + 1) {
+    for enemy_spec in ENEMY_SPECS
+        .iter()
+        .filter(|enemy_spec| enemy_spec.min_difficulty <= *difficulty)
+    {
         // if (level.timer. / this.waveTime).floor() < enemy_spec.waves {
         //     continue;
         // }
 
-        let num =
-            poisson_random(time.delta_seconds() * (0.5 + (enemy_spec.freq)(*difficulty as f32)))
-                .min(MAX_ENEMIES - enemy_count);
+        let num = poisson_random(
+            time.delta_seconds() * (0.5 + (enemy_spec.freq)(*difficulty as f32)),
+            &mut rng,
+        )
+        .min(MAX_ENEMIES - enemy_count);
         for _ in 0..num {
-            let axis = rand::random::<bool>();
-            let side = rand::random::<bool>();
-            let max = if axis { width } else { height };
-
-            let mut x = (rand::random::<f32>() - 0.5) * width;
-            let mut y = if side {
-                -max / 2. + 10.
+            let position = if let Some(position) = map.random_spawn_position(&mut rng) {
+                Position(position)
             } else {
-                max / 2. - 10.
+                return;
             };
 
-            if axis {
-                std::mem::swap(&mut x, &mut y);
+            if !enemy_spec.formations.is_empty() && rng.gen::<f32>() < FORMATION_CHANCE {
+                let formation =
+                    &enemy_spec.formations[rng.gen::<usize>() % enemy_spec.formations.len()];
+                spawn_formation(
+                    &mut commands,
+                    &asset_server,
+                    &arena,
+                    enemy_spec,
+                    formation,
+                    position,
+                    MAX_ENEMIES - enemy_count,
+                    &mut rng,
+                );
+            } else {
+                let mut builder =
+                    spawn_enemy(&mut commands, &asset_server, enemy_spec, position, &mut rng);
+                (enemy_spec.more_components)(&mut builder);
             }
+        }
+    }
+}
 
-            let position = Position(Vec2::new(x, y));
+/// Spawn an enemy entity with the components common to all enemies.
+///
+/// Returns the EntityCommands so that the caller can add behavior components.
+fn spawn_enemy<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    asset_server: &AssetServer,
+    enemy_spec: &EnemySpec,
+    position: Position,
+    rng: &mut GameRng,
+) -> EntityCommands<'w, 's, 'a> {
+    let mut transform = Transform::default();
+    sprite_transform_single(&position, None, &mut transform, 0.05);
+
+    let sprite = commands
+        .spawn_bundle(SpriteBundle {
+            texture: asset_server.load(enemy_spec.image),
+            sprite: Sprite {
+                color: enemy_spec.tint,
+                ..default()
+            },
+            transform: Transform::from_scale(Vec3::splat(enemy_spec.sprite_scale)),
+            ..default()
+        })
+        .insert(BaseTint(enemy_spec.tint))
+        .id();
+
+    let mut builder = commands.spawn_bundle(TransformBundle {
+        local: transform,
+        ..default()
+    });
+
+    builder
+        .insert(position)
+        .insert(Velocity(
+            10. * Vec2::new(rng.gen::<f32>() - 0.5, rng.gen::<f32>() - 0.5),
+        ))
+        .insert(Enemy)
+        .insert(Health::new(enemy_spec.health))
+        .insert(BulletShooter::new(
+            true,
+            enemy_spec.bullet_damage,
+            enemy_spec.bullet_damage_type,
+        ))
+        .insert(enemy_spec.resistances)
+        .insert(StatusEffects::default())
+        .insert(BulletFilter {
+            filter: true,
+            radius: enemy_spec.size,
+            exp: enemy_spec.exp,
+        })
+        .insert(enemy_spec.flocking)
+        .insert(StageClear)
+        .add_child(sprite);
+
+    for ability in enemy_spec.abilities {
+        ability.insert(&mut builder);
+    }
 
-            let mut transform = Transform::default();
-            sprite_transform_single(&position, None, &mut transform, 0.05);
+    if let Some(pattern) = enemy_spec.pattern {
+        builder.insert(BulletPattern::new(pattern));
+    }
 
-            let sprite = commands
-                .spawn_bundle(SpriteBundle {
-                    texture: asset_server.load(enemy_spec.image),
-                    transform: Transform::from_scale(Vec3::splat(enemy_spec.sprite_scale)),
-                    ..default()
-                })
-                .id();
+    if 0. < enemy_spec.armor {
+        builder.insert(Armor(enemy_spec.armor));
+    }
 
-            let mut builder = commands.spawn_bundle(TransformBundle {
-                local: transform,
-                ..default()
-            });
-
-            builder
-                .insert(position)
-                .insert(Velocity(
-                    10. * Vec2::new(rand::random::<f32>() - 0.5, rand::random::<f32>() - 0.5),
-                ))
-                .insert(Enemy)
-                .insert(Health::new(enemy_spec.health))
-                .insert(BulletShooter::new(true, enemy_spec.bullet_damage))
-                .insert(BulletFilter {
-                    filter: true,
-                    radius: enemy_spec.size,
-                    exp: enemy_spec.exp,
-                })
-                .insert(StageClear)
-                .add_child(sprite);
-
-            (enemy_spec.more_components)(&mut builder);
-        }
+    if 0. < enemy_spec.shield {
+        let ring = builder
+            .commands()
+            .spawn_bundle(shield_ring(enemy_spec.size * 1.5))
+            .id();
+        builder
+            .insert(Shield::new(enemy_spec.shield, ring))
+            .add_child(ring);
     }
+
+    builder
 }
 
-fn enemy_system(mut query: Query<&mut Velocity, With<Enemy>>, time: Res<Time>) {
+fn enemy_system(
+    mut query: Query<&mut Velocity, (With<Enemy>, Without<Boss>, Without<FormationSlot>)>,
+    time: Res<SimTime>,
+    mut rng: ResMut<GameRng>,
+) {
     let delta_time = time.delta_seconds();
     for mut velocity in query.iter_mut() {
-        velocity.x +=
-            (-velocity.x * 0.005 + (rand::random::<f32>() - 0.5) * 15.) * 100. * delta_time;
-        velocity.y +=
-            (-velocity.y * 0.005 + (rand::random::<f32>() - 0.5) * 15.) * 100. * delta_time;
+        velocity.x += (-velocity.x * 0.005 + (rng.gen::<f32>() - 0.5) * 15.) * 100. * delta_time;
+        velocity.y += (-velocity.y * 0.005 + (rng.gen::<f32>() - 0.5) * 15.) * 100. * delta_time;
         velocity.x *= 1. - 0.2 * delta_time;
         velocity.y *= 1. - 0.2 * delta_time;
     }
 }
 
+/// Despawn the enemies that ran out of health, however they were damaged, and run their death
+/// abilities. The killers are rewarded exp on their own side.
+fn enemy_death_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    textures: Res<Textures>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut text_writer: EventWriter<FloatingTextEvent>,
+    mut rng: ResMut<GameRng>,
+    query: Query<(Entity, &Position, &Health, &BulletFilter, Option<&Splitter>), With<Enemy>>,
+) {
+    for (entity, position, health, bullet_filter, splitter) in query.iter() {
+        if 0. < health.val {
+            continue;
+        }
+        spawn_large_explosion(&mut commands, &textures, position.0.extend(0.2));
+        scoreboard.score += bullet_filter.exp as f64;
+        scoreboard.credits += bullet_filter.exp as f64;
+        text_writer.send(FloatingTextEvent::exp(position.0, bullet_filter.exp));
+        if let Some(splitter) = splitter {
+            split(&mut commands, &asset_server, splitter, position, &mut rng);
+        }
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
 /// Try to find a closest tower and set its Entity to Target component.
 ///
 /// This enemy will keep targetting the tower until the tower dies.
@@ -307,13 +632,21 @@ fn agile_enemy_system(
             &mut Target,
             &mut BulletShooter,
             &mut AgileEnemy,
+            Option<&StatusEffects>,
         ),
         With<Enemy>,
     >,
     query_towers: Query<(Entity, &Position), With<Tower>>,
 ) {
-    for (mut velocity, position, mut rotation, mut target, mut bullet_shooter, mut agile_enemy) in
-        query.iter_mut()
+    for (
+        mut velocity,
+        position,
+        mut rotation,
+        mut target,
+        mut bullet_shooter,
+        mut agile_enemy,
+        status_effects,
+    ) in query.iter_mut()
     {
         let new_target = try_find_tower(position, target.as_mut(), &query_towers);
 
@@ -337,7 +670,11 @@ fn agile_enemy_system(
             let target_angle = delta.y.atan2(delta.x) as f64;
 
             let enabled;
-            (rotation.0, enabled) = apprach_angle(rotation.0, target_angle, ANGLE_SPEED);
+            (rotation.0, enabled) = apprach_angle(
+                rotation.0,
+                target_angle,
+                ANGLE_SPEED * speed_factor(status_effects) as f64,
+            );
             bullet_shooter.enabled = enabled && !agile_enemy.0;
         }
         velocity.x = (rotation.0.cos() * SPEED) as f32;
@@ -353,12 +690,15 @@ fn sturdy_enemy_system(
             &mut Rotation,
             &mut Target,
             &mut BulletShooter,
+            Option<&StatusEffects>,
         ),
         (With<Enemy>, With<SturdyEnemy>),
     >,
     query_towers: Query<(Entity, &Position), With<Tower>>,
 ) {
-    for (mut velocity, position, mut rotation, mut target, mut bullet_shooter) in query.iter_mut() {
+    for (mut velocity, position, mut rotation, mut target, mut bullet_shooter, status_effects) in
+        query.iter_mut()
+    {
         let new_target = try_find_tower(position, target.as_mut(), &query_towers);
 
         use std::f64::consts::PI;
@@ -370,8 +710,11 @@ fn sturdy_enemy_system(
             let delta = tower_position.0 - position.0;
             let target_angle = delta.y.atan2(delta.x) as f64;
 
-            (rotation.0, bullet_shooter.enabled) =
-                apprach_angle(rotation.0, target_angle, ANGLE_SPEED);
+            (rotation.0, bullet_shooter.enabled) = apprach_angle(
+                rotation.0,
+                target_angle,
+                ANGLE_SPEED * speed_factor(status_effects) as f64,
+            );
             if TOO_CLOSE.powf(2.) < delta.length_squared() {
                 velocity.x = (rotation.0.cos() * SPEED) as f32;
                 velocity.y = (rotation.0.sin() * SPEED) as f32;
@@ -396,7 +739,7 @@ fn missile_enemy_system(
         (With<Enemy>, With<MissileShooter>),
     >,
     query_towers: Query<(Entity, &Position), With<Tower>>,
-    time: Res<Time>,
+    time: Res<SimTime>,
 ) {
     let delta_time = time.delta_seconds();
     for (mut velocity, position, mut rotation, mut target, mut bullet_shooter) in query.iter_mut() {
@@ -428,13 +771,13 @@ fn missile_enemy_system(
 /// A pseudo-random number generator distributed in Poisson distribution.
 /// It uses Knuth's algorithm, which is not optimal when lambda gets
 /// so high.  We probably should use an approximation.
-fn poisson_random(lambda: f32) -> usize {
+fn poisson_random(lambda: f32, rng: &mut GameRng) -> usize {
     let l = (-lambda).exp();
     let mut k = 0;
     let mut p = 1.;
     loop {
         k += 1;
-        p *= rand::random::<f32>();
+        p *= rng.gen::<f32>();
         if p <= l {
             break;
         }
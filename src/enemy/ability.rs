@@ -0,0 +1,239 @@
+use super::{spawn_enemy, Enemy, EnemySpec, MAX_ENEMIES};
+use crate::{
+    simulation::{GameRng, SimTime},
+    status_effect::StatusEffects,
+    tower::{TempEnt, Timeout},
+    BulletFilter, Health, Position, Velocity,
+};
+use bevy::{ecs::system::EntityCommands, prelude::*};
+use rand::Rng;
+
+/// Enemies are spawned around the parent within this distance
+const SPAWN_SPREAD: f32 = 40.;
+const SPLIT_SPEED: f32 = 100.;
+
+pub(crate) enum EnemyAbility {
+    /// Break into `count` enemies of `into` on death
+    Split {
+        into: &'static EnemySpec,
+        count: usize,
+    },
+    /// Spawn `count` enemies of `minion` every `interval` seconds
+    Carry {
+        minion: &'static EnemySpec,
+        count: usize,
+        interval: f32,
+    },
+    /// Heal the most damaged ally within `range` by `amount` every `interval` seconds
+    Heal {
+        range: f32,
+        amount: f32,
+        interval: f32,
+    },
+}
+
+impl EnemyAbility {
+    pub(super) fn insert(&self, builder: &mut EntityCommands) {
+        match *self {
+            Self::Split { into, count } => {
+                builder.insert(Splitter { into, count });
+            }
+            Self::Carry {
+                minion,
+                count,
+                interval,
+            } => {
+                builder.insert(Carrier {
+                    minion,
+                    count,
+                    interval,
+                    cooldown: interval,
+                });
+            }
+            Self::Heal {
+                range,
+                amount,
+                interval,
+            } => {
+                builder.insert(Medic {
+                    range,
+                    amount,
+                    interval,
+                    cooldown: interval,
+                });
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+pub(super) struct Splitter {
+    into: &'static EnemySpec,
+    count: usize,
+}
+
+#[derive(Component)]
+pub(super) struct Carrier {
+    minion: &'static EnemySpec,
+    count: usize,
+    interval: f32,
+    cooldown: f32,
+}
+
+#[derive(Component)]
+pub(super) struct Medic {
+    range: f32,
+    amount: f32,
+    interval: f32,
+    cooldown: f32,
+}
+
+fn random_offset(rng: &mut GameRng) -> Vec2 {
+    Vec2::new(rng.gen::<f32>() - 0.5, rng.gen::<f32>() - 0.5) * 2. * SPAWN_SPREAD
+}
+
+/// Death hook of a `Splitter`, called from `enemy_death_system`.
+pub(super) fn split(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    splitter: &Splitter,
+    position: &Position,
+    rng: &mut GameRng,
+) {
+    for i in 0..splitter.count {
+        let angle = i as f32 * std::f32::consts::TAU / splitter.count as f32;
+        let direction = Vec2::new(angle.cos(), angle.sin());
+        let mut builder = spawn_enemy(
+            commands,
+            asset_server,
+            splitter.into,
+            Position(position.0 + direction * splitter.into.size),
+            rng,
+        );
+        (splitter.into.more_components)(&mut builder);
+        builder.insert(Velocity(direction * SPLIT_SPEED));
+    }
+}
+
+pub(super) fn carrier_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    time: Res<SimTime>,
+    mut rng: ResMut<GameRng>,
+    mut query: Query<(&Position, &mut Carrier, Option<&StatusEffects>)>,
+    query_enemies: Query<&Enemy>,
+) {
+    let delta = time.delta_seconds();
+    let mut enemy_count = query_enemies.iter().count();
+    for (position, mut carrier, status_effects) in query.iter_mut() {
+        if status_effects
+            .map(|status_effects| status_effects.is_stunned())
+            .unwrap_or(false)
+        {
+            continue;
+        }
+        if delta < carrier.cooldown {
+            carrier.cooldown -= delta;
+            continue;
+        }
+        carrier.cooldown += carrier.interval;
+
+        let count = carrier.count.min(MAX_ENEMIES.saturating_sub(enemy_count));
+        for _ in 0..count {
+            let mut builder = spawn_enemy(
+                &mut commands,
+                &asset_server,
+                carrier.minion,
+                Position(position.0 + random_offset(&mut rng)),
+                &mut rng,
+            );
+            (carrier.minion.more_components)(&mut builder);
+        }
+        enemy_count += count;
+    }
+}
+
+/// Mirrors the tower `healer`, but heals the allies of the enemy side.
+pub(super) fn medic_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    time: Res<SimTime>,
+    mut query: Query<(
+        Entity,
+        &Position,
+        &BulletFilter,
+        &mut Medic,
+        Option<&StatusEffects>,
+    )>,
+    mut target_query: Query<(Entity, &Position, &BulletFilter, &mut Health)>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, position, bullet_filter, mut medic, status_effects) in query.iter_mut() {
+        if status_effects
+            .map(|status_effects| status_effects.is_stunned())
+            .unwrap_or(false)
+        {
+            continue;
+        }
+        if delta < medic.cooldown {
+            medic.cooldown -= delta;
+            continue;
+        }
+
+        let target = target_query
+            .iter_mut()
+            .filter(|(target_entity, target_position, target_filter, health)| {
+                *target_entity != entity
+                    && target_filter.filter == bullet_filter.filter
+                    && 0. < health.val
+                    && health.val < health.max
+                    && target_position.0.distance(position.0) < medic.range
+            })
+            .min_by(|a, b| {
+                (a.3.val / a.3.max)
+                    .partial_cmp(&(b.3.val / b.3.max))
+                    .unwrap()
+            });
+
+        let (target_position, mut health) = if let Some((_, target_position, _, health)) = target {
+            (target_position.0, health)
+        } else {
+            medic.cooldown = 0.;
+            continue;
+        };
+
+        health.val = (health.val + medic.amount).min(health.max);
+        medic.cooldown += medic.interval;
+
+        commands
+            .spawn_bundle(SpriteBundle {
+                texture: asset_server.load("heal-effect.png"),
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(20.0, 20.0)),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(Position(target_position))
+            .insert(Velocity(Vec2::new(0., 5.)))
+            .insert(Timeout(medic.interval))
+            .insert(TempEnt);
+
+        let delta = position.0 - target_position;
+        let centroid = (position.0 + target_position) / 2.;
+
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::rgb(1., 0.4, 0.6),
+                    custom_size: Some(Vec2::new(delta.length(), 2.0)),
+                    ..default()
+                },
+                transform: Transform::from_translation(Vec3::new(centroid.x, centroid.y, 0.1))
+                    .with_rotation(Quat::from_rotation_z(delta.y.atan2(delta.x))),
+                ..default()
+            })
+            .insert(Timeout(medic.interval / 2.))
+            .insert(TempEnt);
+    }
+}
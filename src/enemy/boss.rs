@@ -0,0 +1,268 @@
+use super::{spawn_enemy, try_find_tower, EnemySpec, Flocking};
+use crate::{
+    arena::ArenaBounds,
+    bullet::{BulletPattern, BulletShooter, PatternKind, PatternSpec, ENEMY_SIZE},
+    damage::{DamageType, Resistances},
+    map::CurrentMap,
+    simulation::{GameRng, SimTime},
+    tower::Tower,
+    Health, Level, Position, Target, Velocity,
+};
+use bevy::prelude::*;
+use rand::Rng;
+
+/// Pause between phases so that the player notices the change
+const PHASE_TRANSITION_DELAY: f32 = 1.5;
+/// Bosses wander between random waypoints in this fraction of the arena
+const WAYPOINT_AREA: f32 = 0.6;
+
+pub(crate) struct BossPhase {
+    /// The phase starts when the health drops to this fraction of the maximum
+    health_threshold: f32,
+    pattern: PatternSpec,
+    move_speed: f32,
+}
+
+pub(crate) struct BossSpec {
+    pub name: &'static str,
+    enemy: EnemySpec,
+    /// Seconds from the start of the stage
+    arrival: f32,
+    /// Phases in the order of decreasing `health_threshold`
+    phases: &'static [BossPhase],
+}
+
+const BOSS_SPECS: [BossSpec; 2] = [
+    BossSpec {
+        name: "Overseer",
+        enemy: EnemySpec {
+            waves: 0,
+            min_difficulty: 1,
+            image: "boss.png",
+            tint: Color::WHITE,
+            health: 800.,
+            size: ENEMY_SIZE * 2.,
+            sprite_scale: 3.,
+            exp: 1000,
+            bullet_damage: 1.,
+            bullet_damage_type: DamageType::Kinetic,
+            resistances: Resistances::new(1., 1.5, 1.),
+            armor: 0.,
+            shield: 200.,
+            pattern: None,
+            formations: &[],
+            flocking: Flocking::NONE,
+            abilities: &[],
+            more_components: |_| (),
+            freq: |_| 0.,
+        },
+        arrival: 30.,
+        phases: &[
+            BossPhase {
+                health_threshold: 1.,
+                pattern: PatternSpec::new(
+                    PatternKind::Ring { count: 12 },
+                    1.5,
+                    200.,
+                    "enemy-bullet.png",
+                ),
+                move_speed: 40.,
+            },
+            BossPhase {
+                health_threshold: 0.6,
+                pattern: PatternSpec::new(
+                    PatternKind::Spiral {
+                        arms: 3,
+                        angular_velocity: 1.5,
+                    },
+                    0.2,
+                    250.,
+                    "enemy-bullet.png",
+                ),
+                move_speed: 20.,
+            },
+            BossPhase {
+                health_threshold: 0.3,
+                pattern: PatternSpec::new(
+                    PatternKind::Aimed {
+                        count: 5,
+                        spread: 0.6,
+                    },
+                    0.8,
+                    350.,
+                    "agile-enemy-bullet.png",
+                ),
+                move_speed: 80.,
+            },
+        ],
+    },
+    BossSpec {
+        name: "Dreadnought",
+        enemy: EnemySpec {
+            waves: 0,
+            min_difficulty: 3,
+            image: "missile-enemy.png",
+            tint: Color::WHITE,
+            health: 8000.,
+            size: ENEMY_SIZE * 2.5,
+            sprite_scale: 3.5,
+            exp: 8000,
+            bullet_damage: 2.,
+            bullet_damage_type: DamageType::Kinetic,
+            resistances: Resistances::new(0.75, 0.75, 1.25),
+            armor: 2.,
+            shield: 800.,
+            pattern: None,
+            formations: &[],
+            flocking: Flocking::NONE,
+            abilities: &[],
+            more_components: |_| (),
+            freq: |_| 0.,
+        },
+        arrival: 45.,
+        phases: &[
+            BossPhase {
+                health_threshold: 1.,
+                pattern: PatternSpec::new(
+                    PatternKind::Aimed {
+                        count: 3,
+                        spread: 0.3,
+                    },
+                    1.5,
+                    300.,
+                    "agile-enemy-bullet.png",
+                )
+                .with_burst(3, 0.15),
+                move_speed: 30.,
+            },
+            BossPhase {
+                health_threshold: 0.5,
+                pattern: PatternSpec::new(
+                    PatternKind::Ring { count: 24 },
+                    1.2,
+                    180.,
+                    "enemy-bullet.png",
+                ),
+                move_speed: 30.,
+            },
+            BossPhase {
+                health_threshold: 0.2,
+                pattern: PatternSpec::new(
+                    PatternKind::Spiral {
+                        arms: 4,
+                        angular_velocity: -2.,
+                    },
+                    0.15,
+                    300.,
+                    "enemy-bullet.png",
+                ),
+                move_speed: 60.,
+            },
+        ],
+    },
+];
+
+#[derive(Component)]
+pub(crate) struct Boss {
+    pub spec: &'static BossSpec,
+    phase: usize,
+    waypoint: Vec2,
+}
+
+/// Spawn the bosses of the current difficulty when the stage timer reaches their arrival time.
+pub(super) fn spawn_boss_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    current_map: Res<CurrentMap>,
+    time: Res<SimTime>,
+    level: Res<Level>,
+    mut rng: ResMut<GameRng>,
+) {
+    let (difficulty, timer) = if let Level::Running { difficulty, timer } = level.as_ref() {
+        (*difficulty, timer)
+    } else {
+        return;
+    };
+
+    let elapsed = timer.elapsed_secs();
+    let prev_elapsed = elapsed - time.delta_seconds();
+    for spec in BOSS_SPECS.iter() {
+        if difficulty < spec.enemy.min_difficulty
+            || !(prev_elapsed < spec.arrival && spec.arrival <= elapsed)
+        {
+            continue;
+        }
+
+        let position = if let Some(position) = current_map.spec().random_spawn_position(&mut rng) {
+            Position(position)
+        } else {
+            continue;
+        };
+
+        println!("Boss {} arrived", spec.name);
+
+        let first_phase = &spec.phases[0];
+        spawn_enemy(
+            &mut commands,
+            &asset_server,
+            &spec.enemy,
+            position,
+            &mut rng,
+        )
+        .insert(Target(None))
+        .insert(BulletPattern::new(first_phase.pattern))
+        .insert(Boss {
+            spec,
+            phase: 0,
+            waypoint: Vec2::ZERO,
+        });
+    }
+}
+
+/// Switch phases by the remaining health and wander around the arena.
+pub(super) fn boss_system(
+    time: Res<SimTime>,
+    arena: Res<ArenaBounds>,
+    mut rng: ResMut<GameRng>,
+    mut query: Query<(
+        &mut Boss,
+        &Health,
+        &Position,
+        &mut Velocity,
+        &mut Target,
+        &mut BulletShooter,
+        &mut BulletPattern,
+    )>,
+    query_towers: Query<(Entity, &Position), With<Tower>>,
+) {
+    let delta = time.delta_seconds();
+    for (mut boss, health, position, mut velocity, mut target, mut bullet_shooter, mut pattern) in
+        query.iter_mut()
+    {
+        let health_fraction = health.val / health.max;
+        let phase = boss
+            .spec
+            .phases
+            .iter()
+            .rposition(|phase| health_fraction <= phase.health_threshold)
+            .unwrap_or(0);
+        if phase != boss.phase {
+            boss.phase = phase;
+            pattern.set_spec(boss.spec.phases[phase].pattern);
+            bullet_shooter.cooldown = PHASE_TRANSITION_DELAY;
+        }
+
+        try_find_tower(position, target.as_mut(), &query_towers);
+
+        let to_waypoint = boss.waypoint - position.0;
+        let move_speed = boss.spec.phases[boss.phase].move_speed;
+        if to_waypoint.length() < move_speed * delta.max(0.1) {
+            let half_size = arena.half_size() * WAYPOINT_AREA;
+            boss.waypoint = Vec2::new(
+                (rng.gen::<f32>() * 2. - 1.) * half_size.x,
+                (rng.gen::<f32>() * 2. - 1.) * half_size.y,
+            );
+        }
+        velocity.0 = to_waypoint.normalize_or_zero() * move_speed;
+    }
+}
@@ -0,0 +1,80 @@
+use super::Enemy;
+use crate::{
+    simulation::{GameRng, SimTime},
+    Position, Velocity,
+};
+use bevy::prelude::*;
+use rand::Rng;
+
+/// Boids-style tuning that keeps enemies from stacking on each other.
+#[derive(Component, Clone, Copy, Debug)]
+pub(crate) struct Flocking {
+    /// Distance within which other enemies count as neighbors
+    radius: f32,
+    /// Speed of pushing away from neighbors that are very close
+    separation: f32,
+    /// Fraction of the difference to the average velocity of neighbors matched per second
+    alignment: f32,
+}
+
+impl Flocking {
+    /// Not pushed by the others, although the others still avoid this one
+    pub(crate) const NONE: Self = Self::new(0., 0., 0.);
+
+    pub(crate) const fn new(radius: f32, separation: f32, alignment: f32) -> Self {
+        Self {
+            radius,
+            separation,
+            alignment,
+        }
+    }
+}
+
+pub(super) fn flocking_system(
+    time: Res<SimTime>,
+    mut rng: ResMut<GameRng>,
+    mut query: Query<(Entity, &mut Position, &Velocity, &Flocking), With<Enemy>>,
+) {
+    let delta = time.delta_seconds();
+    let neighbors: Vec<_> = query
+        .iter()
+        .map(|(entity, position, velocity, _)| (entity, position.0, velocity.0))
+        .collect();
+
+    for (entity, mut position, velocity, flocking) in query.iter_mut() {
+        if flocking.radius <= 0. {
+            continue;
+        }
+
+        let mut push = Vec2::ZERO;
+        let mut velocity_sum = Vec2::ZERO;
+        let mut count = 0;
+        for (other, other_position, other_velocity) in &neighbors {
+            if *other == entity {
+                continue;
+            }
+            let diff = position.0 - *other_position;
+            let dist = diff.length();
+            if flocking.radius <= dist {
+                continue;
+            }
+            // Push harder the closer they are. Enemies on the exact same pixel separate in a
+            // random direction.
+            let direction = if 0. < dist {
+                diff / dist
+            } else {
+                Vec2::new(rng.gen::<f32>() - 0.5, rng.gen::<f32>() - 0.5).normalize_or_zero()
+            };
+            push += direction * (1. - dist / flocking.radius);
+            velocity_sum += *other_velocity;
+            count += 1;
+        }
+
+        if count == 0 {
+            continue;
+        }
+
+        let alignment = (velocity_sum / count as f32 - velocity.0) * flocking.alignment;
+        position.0 += (push * flocking.separation + alignment) * delta;
+    }
+}
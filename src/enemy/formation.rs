@@ -0,0 +1,161 @@
+use super::{spawn_enemy, EnemySpec};
+use crate::{
+    arena::ArenaBounds,
+    simulation::{GameRng, SimTime},
+    Position, StageClear, Velocity,
+};
+use bevy::prelude::*;
+use rand::Rng;
+use std::collections::HashSet;
+
+/// Formations wander between random waypoints in this fraction of the arena
+const WAYPOINT_AREA: f32 = 0.8;
+/// How quickly members catch up with their slots, per second
+const FORMATION_STIFFNESS: f32 = 2.;
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum FormationShape {
+    /// A leader followed by pairs trailing diagonally behind
+    V,
+    /// Side by side, perpendicular to the heading
+    Line,
+    Circle,
+}
+
+impl FormationShape {
+    /// Slot offsets relative to the anchor, in a frame where +x is the heading
+    fn offsets(&self, count: usize, spacing: f32) -> Vec<Vec2> {
+        (0..count)
+            .map(|i| match self {
+                Self::V => {
+                    let rank = (i as f32 / 2.).ceil();
+                    let side = if i % 2 == 0 { 1. } else { -1. };
+                    Vec2::new(-rank * spacing, side * rank * spacing)
+                }
+                Self::Line => Vec2::new(0., (i as f32 - (count - 1) as f32 / 2.) * spacing),
+                Self::Circle => {
+                    let radius = spacing * count as f32 / std::f32::consts::TAU;
+                    let angle = i as f32 * std::f32::consts::TAU / count as f32;
+                    Vec2::new(angle.cos(), angle.sin()) * radius
+                }
+            })
+            .collect()
+    }
+}
+
+pub(crate) struct FormationSpec {
+    pub shape: FormationShape,
+    pub count: usize,
+    /// Distance between neighboring members
+    pub spacing: f32,
+    pub speed: f32,
+}
+
+/// An invisible entity that a group of enemies follows.
+#[derive(Component)]
+pub(super) struct FormationAnchor {
+    waypoint: Vec2,
+    speed: f32,
+    /// Direction the formation faces, kept while the anchor is not moving
+    heading: Vec2,
+}
+
+/// An enemy holding a slot in a formation. Its own movement is replaced by following the slot.
+#[derive(Component)]
+pub(super) struct FormationSlot {
+    anchor: Entity,
+    offset: Vec2,
+}
+
+fn random_waypoint(arena: &ArenaBounds, rng: &mut GameRng) -> Vec2 {
+    let half_size = arena.half_size() * WAYPOINT_AREA;
+    Vec2::new(
+        (rng.gen::<f32>() * 2. - 1.) * half_size.x,
+        (rng.gen::<f32>() * 2. - 1.) * half_size.y,
+    )
+}
+
+/// Spawn up to `max_count` enemies of `enemy_spec` in a formation centered at `position`.
+pub(super) fn spawn_formation(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    arena: &ArenaBounds,
+    enemy_spec: &EnemySpec,
+    formation: &FormationSpec,
+    position: Position,
+    max_count: usize,
+    rng: &mut GameRng,
+) {
+    let waypoint = random_waypoint(arena, rng);
+    let heading = (waypoint - position.0).normalize_or_zero();
+    let anchor = commands
+        .spawn()
+        .insert(position)
+        .insert(Velocity(heading * formation.speed))
+        .insert(FormationAnchor {
+            waypoint,
+            speed: formation.speed,
+            heading,
+        })
+        .insert(StageClear)
+        .id();
+
+    for offset in formation
+        .shape
+        .offsets(formation.count.min(max_count), formation.spacing)
+    {
+        let slot_position = Position(position.0 + heading * offset.x + heading.perp() * offset.y);
+        let mut builder = spawn_enemy(commands, asset_server, enemy_spec, slot_position, rng);
+        (enemy_spec.more_components)(&mut builder);
+        builder.insert(FormationSlot { anchor, offset });
+    }
+}
+
+pub(super) fn formation_system(
+    mut commands: Commands,
+    time: Res<SimTime>,
+    arena: Res<ArenaBounds>,
+    mut rng: ResMut<GameRng>,
+    mut query_anchors: Query<(Entity, &Position, &mut Velocity, &mut FormationAnchor)>,
+    mut query_members: Query<
+        (Entity, &Position, &mut Velocity, &FormationSlot),
+        Without<FormationAnchor>,
+    >,
+) {
+    let delta = time.delta_seconds();
+    for (_, position, mut velocity, mut anchor) in query_anchors.iter_mut() {
+        if (anchor.waypoint - position.0).length() < anchor.speed * delta.max(0.1) {
+            anchor.waypoint = random_waypoint(&arena, &mut rng);
+        }
+        let direction = (anchor.waypoint - position.0).normalize_or_zero();
+        if direction != Vec2::ZERO {
+            anchor.heading = direction;
+        }
+        velocity.0 = direction * anchor.speed;
+    }
+
+    let mut alive_anchors = HashSet::new();
+    for (entity, position, mut velocity, slot) in query_members.iter_mut() {
+        let (anchor_position, anchor_velocity, anchor) =
+            if let Ok((_, position, velocity, anchor)) = query_anchors.get(slot.anchor) {
+                (position, velocity, anchor)
+            } else {
+                // The formation is gone, so go back to moving on its own
+                commands.entity(entity).remove::<FormationSlot>();
+                continue;
+            };
+        alive_anchors.insert(slot.anchor);
+
+        let slot_position = anchor_position.0
+            + anchor.heading * slot.offset.x
+            + anchor.heading.perp() * slot.offset.y;
+        velocity.0 = anchor_velocity.0 + (slot_position - position.0) * FORMATION_STIFFNESS;
+    }
+
+    // Remove the anchors whose members have all died
+    for (entity, ..) in query_anchors.iter() {
+        if !alive_anchors.contains(&entity) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
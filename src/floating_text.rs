@@ -0,0 +1,213 @@
+use crate::settings::Settings;
+use bevy::prelude::*;
+
+/// Number of text entities reused for all floating texts
+const POOL_SIZE: usize = 64;
+const LIFETIME: f32 = 0.8;
+const RISE_SPEED: f32 = 40.;
+/// Damage to the same target within this many seconds adds up in the same text
+const MERGE_TIME: f32 = 0.3;
+const FONT_SIZE: f32 = 16.;
+
+pub(crate) struct FloatingTextPlugin;
+
+impl Plugin for FloatingTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FloatingTextEvent>();
+        app.add_startup_system(setup_pool);
+        app.add_system(floating_text_event_system);
+        app.add_system(animate_floating_text.after(floating_text_event_system));
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum FloatingTextKind {
+    Damage(f32),
+    Exp(usize),
+    LevelUp,
+}
+
+pub(crate) struct FloatingTextEvent {
+    pub position: Vec2,
+    pub kind: FloatingTextKind,
+    /// The entity the text is about, used to merge continuous damage like the beam
+    pub target: Option<Entity>,
+}
+
+impl FloatingTextEvent {
+    pub(crate) fn damage(target: Entity, position: Vec2, damage: f32) -> Self {
+        Self {
+            position,
+            kind: FloatingTextKind::Damage(damage),
+            target: Some(target),
+        }
+    }
+
+    pub(crate) fn exp(position: Vec2, exp: usize) -> Self {
+        Self {
+            position,
+            kind: FloatingTextKind::Exp(exp),
+            target: None,
+        }
+    }
+
+    pub(crate) fn level_up(position: Vec2) -> Self {
+        Self {
+            position,
+            kind: FloatingTextKind::LevelUp,
+            target: None,
+        }
+    }
+}
+
+#[derive(Component)]
+struct FloatingText {
+    active: bool,
+    age: f32,
+    kind: FloatingTextKind,
+    target: Option<Entity>,
+    color: Color,
+}
+
+impl FloatingTextKind {
+    fn text(&self) -> String {
+        match self {
+            Self::Damage(damage) => format!("{:.0}", damage.max(1.)),
+            Self::Exp(exp) => format!("+{} exp", exp),
+            Self::LevelUp => "LEVEL UP".to_string(),
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            Self::Damage(_) => Color::rgb(1., 1., 1.),
+            Self::Exp(_) => Color::rgb(0.5, 1., 0.5),
+            Self::LevelUp => Color::rgb(1., 0.9, 0.3),
+        }
+    }
+}
+
+fn setup_pool(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let style = TextStyle {
+        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+        font_size: FONT_SIZE,
+        color: Color::WHITE,
+    };
+    for _ in 0..POOL_SIZE {
+        commands
+            .spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    "",
+                    style.clone(),
+                    TextAlignment {
+                        vertical: VerticalAlign::Center,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                visibility: Visibility { is_visible: false },
+                ..default()
+            })
+            .insert(FloatingText {
+                active: false,
+                age: 0.,
+                kind: FloatingTextKind::LevelUp,
+                target: None,
+                color: Color::WHITE,
+            });
+    }
+}
+
+/// Show a text from the pool for each event, reusing the oldest one if all are in use.
+fn floating_text_event_system(
+    settings: Res<Settings>,
+    mut reader: EventReader<FloatingTextEvent>,
+    mut query: Query<(
+        Entity,
+        &mut FloatingText,
+        &mut Text,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    if !settings.damage_numbers {
+        // Drain the events so that they don't pop up when turned back on
+        reader.iter().for_each(drop);
+        return;
+    }
+
+    for event in reader.iter() {
+        if let (FloatingTextKind::Damage(damage), Some(target)) = (event.kind, event.target) {
+            if let Some((_, mut floating_text, mut text, ..)) =
+                query.iter_mut().find(|(_, ft, ..)| {
+                    ft.active
+                        && ft.target == Some(target)
+                        && ft.age < MERGE_TIME
+                        && matches!(ft.kind, FloatingTextKind::Damage(_))
+                })
+            {
+                if let FloatingTextKind::Damage(ref mut sum) = floating_text.kind {
+                    *sum += damage;
+                }
+                text.sections[0].value = floating_text.kind.text();
+                continue;
+            }
+        }
+
+        let slot = query
+            .iter()
+            .find(|(_, ft, ..)| !ft.active)
+            .or_else(|| {
+                query.iter().max_by(|(_, a, ..), (_, b, ..)| {
+                    a.age
+                        .partial_cmp(&b.age)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+            })
+            .map(|(entity, ..)| entity);
+        let (_, mut floating_text, mut text, mut transform, mut visibility) =
+            if let Some(slot) = slot.and_then(|slot| query.get_mut(slot).ok()) {
+                slot
+            } else {
+                continue;
+            };
+
+        *floating_text = FloatingText {
+            active: true,
+            age: 0.,
+            kind: event.kind,
+            target: event.target,
+            color: event.kind.color(),
+        };
+        text.sections[0].value = event.kind.text();
+        text.sections[0].style.color = floating_text.color;
+        *transform = Transform::from_translation(event.position.extend(0.5));
+        visibility.is_visible = true;
+    }
+}
+
+fn animate_floating_text(
+    time: Res<Time>,
+    mut query: Query<(
+        &mut FloatingText,
+        &mut Text,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+) {
+    let delta = time.delta_seconds();
+    for (mut floating_text, mut text, mut transform, mut visibility) in query.iter_mut() {
+        if !floating_text.active {
+            continue;
+        }
+        floating_text.age += delta;
+        if LIFETIME < floating_text.age {
+            floating_text.active = false;
+            visibility.is_visible = false;
+            continue;
+        }
+        transform.translation.y += RISE_SPEED * delta;
+        let mut color = floating_text.color;
+        color.set_a(1. - floating_text.age / LIFETIME);
+        text.sections[0].style.color = color;
+    }
+}
@@ -1,17 +1,41 @@
+mod arena;
 mod bullet;
+mod command;
+mod damage;
 mod enemy;
+mod floating_text;
+mod map;
 mod mouse;
+mod replay;
 mod save;
+mod settings;
+mod simulation;
+mod sound;
+mod stats;
+mod status_effect;
 mod tower;
 mod ui;
+mod undo;
 
 use crate::{
+    arena::ArenaPlugin,
     bullet::BulletPlugin,
+    command::CommandPlugin,
+    damage::DamagePlugin,
     enemy::{Enemy, EnemyPlugin},
-    mouse::{tower_not_dragging, MousePlugin},
+    floating_text::FloatingTextPlugin,
+    map::MapPlugin,
+    mouse::{tower_not_dragging, MousePlugin, PendingPlacement},
+    replay::{Playback, ReplayPlugin},
     save::{load_game, save_game, SaveGameEvent},
+    settings::SettingsPlugin,
+    simulation::{SimTime, SimulationPlugin, SimulationStage},
+    sound::SoundPlugin,
+    stats::StatsPlugin,
+    status_effect::{speed_factor, StatusEffectPlugin, StatusEffects},
     tower::{spawn_towers, update_health_bar, Tower, TowerPlugin},
     ui::UIPlugin,
+    undo::UndoPlugin,
 };
 use bevy::{ecs::schedule::ShouldRun, prelude::*};
 use mouse::SelectedTower;
@@ -21,23 +45,52 @@ use ui::{not_paused, PauseState};
 
 const MAX_DIFFICULTY: usize = 5;
 
+/// How a stage of each difficulty begins
+const BUILD_PHASES: [BuildPhase; MAX_DIFFICULTY] = [
+    BuildPhase::UntilStart,
+    BuildPhase::UntilStart,
+    BuildPhase::Countdown(30.),
+    BuildPhase::Countdown(15.),
+    BuildPhase::None,
+];
+
+enum BuildPhase {
+    /// The wave starts when the player presses the Start button
+    UntilStart,
+    /// The wave starts after the seconds, or earlier with the Start button
+    Countdown(f32),
+    None,
+}
+
 fn main() {
     App::new()
         .add_event::<ClearEvent>()
         .add_event::<SaveGameEvent>()
         .insert_resource(ClearColor(Color::rgb(0.1, 0.1, 0.2)))
         .add_plugins(DefaultPlugins)
+        .add_plugin(SimulationPlugin)
+        .add_plugin(SettingsPlugin)
+        .add_plugin(ArenaPlugin)
+        .add_plugin(MapPlugin)
         .add_plugin(UIPlugin)
         .add_plugin(TowerPlugin)
         .add_plugin(BulletPlugin)
+        .add_plugin(DamagePlugin)
+        .add_plugin(StatusEffectPlugin)
+        .add_plugin(StatsPlugin)
         .add_plugin(MousePlugin)
+        .add_plugin(CommandPlugin)
         .add_plugin(EnemyPlugin)
+        .add_plugin(FloatingTextPlugin)
+        .add_plugin(SoundPlugin)
+        .add_plugin(ReplayPlugin)
+        .add_plugin(UndoPlugin)
         .add_startup_system(setup)
-        .add_system_set(
+        .add_system_set_to_stage(
+            SimulationStage,
             SystemSet::new()
-                .with_run_criteria(can_update)
                 .with_system(time_level)
-                .with_system(timeout_level)
+                .with_system(timeout_level.after(time_level))
                 .with_system(linear_motion)
                 .with_system(animate_sprite),
         )
@@ -130,10 +183,33 @@ struct StageScore {
 
 enum Level {
     Select,
-    Running { difficulty: usize, timer: Timer },
+    /// Towers can be bought and moved before the enemies come
+    Build {
+        difficulty: usize,
+        countdown: Option<Timer>,
+    },
+    Running {
+        difficulty: usize,
+        timer: Timer,
+    },
 }
 
 impl Level {
+    /// Begin the build phase of the stage, or the stage itself if the difficulty has none.
+    fn build(difficulty: usize) -> Self {
+        match BUILD_PHASES.get(difficulty) {
+            Some(BuildPhase::UntilStart) => Self::Build {
+                difficulty,
+                countdown: None,
+            },
+            Some(BuildPhase::Countdown(secs)) => Self::Build {
+                difficulty,
+                countdown: Some(Timer::from_seconds(*secs, false)),
+            },
+            _ => Self::start(difficulty),
+        }
+    }
+
     fn start(difficulty: usize) -> Self {
         Self::Running {
             difficulty,
@@ -143,11 +219,16 @@ impl Level {
 
     fn timer_finished(&self) -> bool {
         match self {
-            Self::Select => false,
+            Self::Select | Self::Build { .. } => false,
             Self::Running { timer, .. } => timer.just_finished(),
         }
     }
 
+    /// Whether the player can buy and move towers
+    fn can_build(&self) -> bool {
+        matches!(self, Self::Build { .. } | Self::Running { .. })
+    }
+
     fn _is_running(&self) -> bool {
         if let Self::Running { .. } = self {
             true
@@ -157,10 +238,9 @@ impl Level {
     }
 
     fn _difficulty(&self) -> usize {
-        if let Self::Running { difficulty, .. } = self {
-            *difficulty
-        } else {
-            0
+        match self {
+            Self::Select => 0,
+            Self::Build { difficulty, .. } | Self::Running { difficulty, .. } => *difficulty,
         }
     }
 }
@@ -191,18 +271,25 @@ fn setup(
 
     commands.spawn_bundle(UiCameraBundle::default());
 
-    commands.spawn_bundle(SpriteBundle {
-        texture: asset_server.load("cliff-crop.png"),
-        transform: Transform::from_scale(Vec3::ONE * 2.),
-        ..default()
-    });
-
     // spawn_towers(&mut commands, &asset_server);
 }
 
-fn time_level(mut level: ResMut<Level>, time: Res<Time>) {
-    if let Level::Running { timer, .. } = level.as_mut() {
-        timer.tick(time.delta());
+fn time_level(mut level: ResMut<Level>, time: Res<SimTime>) {
+    match level.as_mut() {
+        Level::Build {
+            difficulty,
+            countdown: Some(countdown),
+        } => {
+            countdown.tick(time.delta());
+            if countdown.finished() {
+                let difficulty = *difficulty;
+                *level = Level::start(difficulty);
+            }
+        }
+        Level::Running { timer, .. } => {
+            timer.tick(time.delta());
+        }
+        _ => (),
     }
 }
 
@@ -223,6 +310,7 @@ fn reset_game(
     mut writer: EventWriter<SaveGameEvent>,
     mut scoreboard: ResMut<Scoreboard>,
     asset_server: Res<AssetServer>,
+    playback: Res<Playback>,
 ) {
     if reader.iter().next().is_some() {
         println!("Round finished!");
@@ -230,6 +318,12 @@ fn reset_game(
             commands.entity(entity).despawn_recursive();
         }
 
+        // A replay doesn't count for the progress, and the towers are restored from the save
+        if playback.is_playing() {
+            *level = Level::Select;
+            return;
+        }
+
         // Restore full health on stage clear
         let mut any_tower = false;
         for mut tower_health in query_towers.iter_mut() {
@@ -260,9 +354,12 @@ fn reset_game(
     }
 }
 
-fn linear_motion(time: Res<Time>, mut query: Query<(&mut Position, &Velocity)>) {
-    for (mut position, velocity) in query.iter_mut() {
-        position.0 += velocity.0 * time.delta_seconds();
+fn linear_motion(
+    time: Res<SimTime>,
+    mut query: Query<(&mut Position, &Velocity, Option<&StatusEffects>)>,
+) {
+    for (mut position, velocity, status_effects) in query.iter_mut() {
+        position.0 += velocity.0 * speed_factor(status_effects) * time.delta_seconds();
     }
 }
 
@@ -299,7 +396,7 @@ fn sprite_transform_single(
 
 fn animate_sprite(
     mut commands: Commands,
-    time: Res<Time>,
+    time: Res<SimTime>,
     texture_atlases: Res<Assets<TextureAtlas>>,
     mut query: Query<(
         Entity,
@@ -321,8 +418,12 @@ fn animate_sprite(
     }
 }
 
-fn can_update(selected_tower: Res<SelectedTower>, pause_state: Res<PauseState>) -> ShouldRun {
-    if tower_not_dragging(selected_tower) == ShouldRun::Yes
+fn can_update(
+    selected_tower: Res<SelectedTower>,
+    pending: Res<PendingPlacement>,
+    pause_state: Res<PauseState>,
+) -> ShouldRun {
+    if tower_not_dragging(selected_tower, pending) == ShouldRun::Yes
         && not_paused(pause_state) == ShouldRun::Yes
     {
         ShouldRun::Yes
@@ -0,0 +1,345 @@
+use crate::{
+    arena::ArenaBounds,
+    enemy::Enemy,
+    simulation::{GameRng, SimulationStage},
+    Position, Velocity,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use rand::Rng;
+
+pub(crate) struct MapPlugin;
+
+impl Plugin for MapPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CurrentMap(0));
+        app.add_startup_system(add_background);
+        app.add_system(update_map_system);
+        app.add_system_to_stage(SimulationStage, terrain_collision_system);
+    }
+}
+
+/// An axis-aligned rectangle in world coordinates.
+///
+/// We don't use `Vec2` here because its constructor is not `const fn`, so we couldn't define
+/// the map specs as constants.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MapRect {
+    pub left: f32,
+    pub bottom: f32,
+    pub right: f32,
+    pub top: f32,
+}
+
+impl MapRect {
+    const fn new(left: f32, bottom: f32, right: f32, top: f32) -> Self {
+        Self {
+            left,
+            bottom,
+            right,
+            top,
+        }
+    }
+
+    fn contains(&self, position: Vec2) -> bool {
+        self.left <= position.x
+            && position.x <= self.right
+            && self.bottom <= position.y
+            && position.y <= self.top
+    }
+
+    /// Returns whether the circle of `radius` at `position` overlaps this rectangle.
+    fn intersects_circle(&self, position: Vec2, radius: f32) -> bool {
+        let nearest = position.clamp(
+            Vec2::new(self.left, self.bottom),
+            Vec2::new(self.right, self.top),
+        );
+        nearest.distance_squared(position) < radius.powf(2.)
+    }
+
+    fn center(&self) -> Vec2 {
+        Vec2::new((self.left + self.right) / 2., (self.bottom + self.top) / 2.)
+    }
+
+    fn size(&self) -> Vec2 {
+        Vec2::new(self.right - self.left, self.top - self.bottom)
+    }
+
+    fn random_point(&self, rng: &mut GameRng) -> Vec2 {
+        Vec2::new(
+            self.left + rng.gen::<f32>() * (self.right - self.left),
+            self.bottom + rng.gen::<f32>() * (self.top - self.bottom),
+        )
+    }
+}
+
+pub(crate) struct SpawnZone {
+    pub rect: MapRect,
+    /// Relative probability of an enemy to spawn in this zone
+    pub weight: f32,
+}
+
+pub(crate) struct MapSpec {
+    pub name: &'static str,
+    pub background: &'static str,
+    pub background_color: Color,
+    pub arena: ArenaBounds,
+    pub spawn_zones: &'static [SpawnZone],
+    /// Towers cannot be placed here, but enemies and bullets pass freely
+    pub unbuildable: &'static [MapRect],
+    /// Towers cannot be placed here, and enemies and bullets cannot pass through
+    pub blocking: &'static [MapRect],
+}
+
+impl MapSpec {
+    /// Pick a random spawn position with probability proportional to the weights of the zones.
+    pub(crate) fn random_spawn_position(&self, rng: &mut GameRng) -> Option<Vec2> {
+        let total: f32 = self.spawn_zones.iter().map(|zone| zone.weight).sum();
+        let mut pick = rng.gen::<f32>() * total;
+        for zone in self.spawn_zones {
+            if pick < zone.weight {
+                return Some(zone.rect.random_point(rng));
+            }
+            pick -= zone.weight;
+        }
+        self.spawn_zones
+            .last()
+            .map(|zone| zone.rect.random_point(rng))
+    }
+
+    pub(crate) fn is_buildable(&self, position: Vec2, radius: f32) -> bool {
+        !self
+            .unbuildable
+            .iter()
+            .chain(self.blocking.iter())
+            .any(|rect| rect.intersects_circle(position, radius))
+    }
+
+    pub(crate) fn is_blocked(&self, position: Vec2) -> bool {
+        self.blocking.iter().any(|rect| rect.contains(position))
+    }
+}
+
+const SPAWN_MARGIN: f32 = 10.;
+
+/// Spawn zones along all four edges of the arena with the same weight
+macro_rules! edge_spawn_zones {
+    {$width:expr, $height:expr} => {
+        &[
+            SpawnZone {
+                rect: MapRect::new(
+                    -$width / 2.,
+                    -$height / 2.,
+                    -$width / 2. + SPAWN_MARGIN,
+                    $height / 2.,
+                ),
+                weight: 1.,
+            },
+            SpawnZone {
+                rect: MapRect::new(
+                    $width / 2. - SPAWN_MARGIN,
+                    -$height / 2.,
+                    $width / 2.,
+                    $height / 2.,
+                ),
+                weight: 1.,
+            },
+            SpawnZone {
+                rect: MapRect::new(
+                    -$width / 2.,
+                    -$height / 2.,
+                    $width / 2.,
+                    -$height / 2. + SPAWN_MARGIN,
+                ),
+                weight: 1.,
+            },
+            SpawnZone {
+                rect: MapRect::new(
+                    -$width / 2.,
+                    $height / 2. - SPAWN_MARGIN,
+                    $width / 2.,
+                    $height / 2.,
+                ),
+                weight: 1.,
+            },
+        ]
+    }
+}
+
+pub(crate) const MAP_SPECS: [MapSpec; 3] = [
+    MapSpec {
+        name: "Cliff",
+        background: "cliff-crop.png",
+        background_color: Color::WHITE,
+        arena: ArenaBounds::new(1280., 720.),
+        spawn_zones: edge_spawn_zones!(1280., 720.),
+        unbuildable: &[],
+        blocking: &[],
+    },
+    MapSpec {
+        name: "Canyon",
+        background: "cliff-crop.png",
+        background_color: Color::rgb(1., 0.8, 0.6),
+        arena: ArenaBounds::new(1440., 720.),
+        spawn_zones: &[
+            SpawnZone {
+                rect: MapRect::new(-720., -360., -710., 360.),
+                weight: 3.,
+            },
+            SpawnZone {
+                rect: MapRect::new(710., -360., 720., 360.),
+                weight: 3.,
+            },
+            SpawnZone {
+                rect: MapRect::new(-720., 350., 720., 360.),
+                weight: 1.,
+            },
+        ],
+        unbuildable: &[MapRect::new(-720., -40., 720., 40.)],
+        blocking: &[
+            MapRect::new(-400., 120., -250., 260.),
+            MapRect::new(250., -260., 400., -120.),
+        ],
+    },
+    MapSpec {
+        name: "Fortress",
+        background: "cliff-crop.png",
+        background_color: Color::rgb(0.6, 0.7, 0.9),
+        arena: ArenaBounds::new(1280., 960.),
+        spawn_zones: edge_spawn_zones!(1280., 960.),
+        unbuildable: &[
+            MapRect::new(-640., -480., -400., 480.),
+            MapRect::new(400., -480., 640., 480.),
+        ],
+        blocking: &[
+            MapRect::new(-250., 200., 250., 230.),
+            MapRect::new(-250., -230., 250., -200.),
+        ],
+    },
+];
+
+/// Index into `MAP_SPECS` of the map to play
+pub(crate) struct CurrentMap(pub usize);
+
+impl CurrentMap {
+    pub(crate) fn spec(&self) -> &'static MapSpec {
+        &MAP_SPECS[self.0 % MAP_SPECS.len()]
+    }
+}
+
+#[derive(Component)]
+struct MapBackground;
+
+/// Marker component for shapes that visualize map terrain
+#[derive(Component)]
+struct MapTerrain;
+
+fn add_background(mut commands: Commands) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            transform: Transform::from_scale(Vec3::ONE * 2.),
+            ..default()
+        })
+        .insert(MapBackground);
+}
+
+fn update_map_system(
+    mut commands: Commands,
+    current_map: Res<CurrentMap>,
+    asset_server: Res<AssetServer>,
+    mut arena: ResMut<ArenaBounds>,
+    mut query_background: Query<(&mut Handle<Image>, &mut Sprite), With<MapBackground>>,
+    query_terrain: Query<Entity, With<MapTerrain>>,
+) {
+    if !current_map.is_changed() {
+        return;
+    }
+
+    let spec = current_map.spec();
+    *arena = spec.arena;
+
+    for (mut texture, mut sprite) in query_background.iter_mut() {
+        *texture = asset_server.load(spec.background);
+        sprite.color = spec.background_color;
+    }
+
+    for entity in query_terrain.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let mut spawn_rect = |rect: &MapRect, fill: Color, stroke: Color| {
+        let shape = shapes::Rectangle {
+            extents: rect.size(),
+            origin: RectangleOrigin::Center,
+        };
+        let center = rect.center();
+        commands
+            .spawn_bundle(GeometryBuilder::build_as(
+                &shape,
+                DrawMode::Outlined {
+                    fill_mode: FillMode::color(fill),
+                    outline_mode: StrokeMode::new(stroke, 2.),
+                },
+                Transform::from_xyz(center.x, center.y, 0.02),
+            ))
+            .insert(MapTerrain);
+    };
+
+    for rect in spec.unbuildable {
+        spawn_rect(
+            rect,
+            Color::rgba(0.8, 0.2, 0.2, 0.15),
+            Color::rgba(0.8, 0.2, 0.2, 0.4),
+        );
+    }
+
+    for rect in spec.blocking {
+        spawn_rect(
+            rect,
+            Color::rgba(0.3, 0.25, 0.2, 0.9),
+            Color::rgba(0.6, 0.5, 0.4, 1.),
+        );
+    }
+}
+
+/// Push enemies out of blocking terrain and reflect their velocity, like the arena edges do.
+fn terrain_collision_system(
+    current_map: Res<CurrentMap>,
+    mut query: Query<(&mut Position, &mut Velocity), With<Enemy>>,
+) {
+    let spec = current_map.spec();
+    if spec.blocking.is_empty() {
+        return;
+    }
+    for (mut position, mut velocity) in query.iter_mut() {
+        for rect in spec.blocking {
+            if !rect.contains(position.0) {
+                continue;
+            }
+            // Find the nearest edge to push the enemy out of
+            let escapes = [
+                (position.0.x - rect.left, Vec2::new(rect.left, position.0.y)),
+                (
+                    rect.right - position.0.x,
+                    Vec2::new(rect.right, position.0.y),
+                ),
+                (
+                    position.0.y - rect.bottom,
+                    Vec2::new(position.0.x, rect.bottom),
+                ),
+                (rect.top - position.0.y, Vec2::new(position.0.x, rect.top)),
+            ];
+            let (i, (_, escape)) = escapes
+                .iter()
+                .enumerate()
+                .min_by(|a, b| a.1 .0.partial_cmp(&b.1 .0).unwrap())
+                .unwrap();
+            position.0 = *escape;
+            if i < 2 {
+                velocity.x = -velocity.x;
+            } else {
+                velocity.y = -velocity.y;
+            }
+        }
+    }
+}
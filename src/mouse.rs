@@ -1,20 +1,39 @@
-use crate::{tower::Tower, Position};
-use bevy::{
-    ecs::{schedule::ShouldRun, system::QueryComponentError},
-    prelude::*,
+use crate::{
+    arena::ArenaBounds,
+    command::{placement_error, PlayerCommand},
+    map::CurrentMap,
+    replay::Playback,
+    settings::{KeyAction, Settings},
+    tower::{
+        angle_to, AimMode, Targeting, Tower, TowerLevel, DEFAULT_HOLD_ARC, MAX_HOLD_ARC,
+        MIN_HOLD_ARC,
+    },
+    ui::{TowerPalette, TowerTypeQuery},
+    Position,
 };
+use bevy::{ecs::schedule::ShouldRun, input::mouse::MouseWheel, prelude::*};
+use bevy_prototype_lyon::{entity::ShapeBundle, prelude::*, shapes::Circle};
 
 pub(crate) struct MousePlugin;
 
 impl Plugin for MousePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(SelectedTower::None);
+        app.insert_resource(TowerSelection::default());
+        app.insert_resource(PendingPlacement(None));
+        app.insert_resource(HoveringTrashcan(false));
         app.add_startup_system(setup);
         app.add_system(mouse_system);
+        app.add_system(selection_system);
+        app.add_system(selection_marker_system);
+        app.add_system(placement_preview_system);
+        app.add_system(targeting_key_system);
+        app.add_system(aim_key_system);
+        app.add_system(hold_arc_wheel_system);
     }
 }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, arena: Res<ArenaBounds>) {
     commands
         .spawn_bundle(SpriteBundle {
             texture: asset_server.load("select-marker.png"),
@@ -25,6 +44,46 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
             ..default()
         })
         .insert(MouseCursor);
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color: SELECTION_BOX_COLOR,
+                ..default()
+            },
+            visibility: Visibility { is_visible: false },
+            ..default()
+        })
+        .insert(SelectionBox);
+
+    commands
+        .spawn_bundle(grid_bundle(&arena))
+        .insert(PlacementGrid);
+}
+
+fn grid_bundle(arena: &ArenaBounds) -> ShapeBundle {
+    let half = arena.half_size();
+    let mut path_builder = PathBuilder::new();
+    let mut x = -(half.x / GRID_SIZE).floor() * GRID_SIZE;
+    while x <= half.x {
+        path_builder.move_to(Vec2::new(x, -half.y));
+        path_builder.line_to(Vec2::new(x, half.y));
+        x += GRID_SIZE;
+    }
+    let mut y = -(half.y / GRID_SIZE).floor() * GRID_SIZE;
+    while y <= half.y {
+        path_builder.move_to(Vec2::new(-half.x, y));
+        path_builder.line_to(Vec2::new(half.x, y));
+        y += GRID_SIZE;
+    }
+
+    let mut bundle = GeometryBuilder::build_as(
+        &path_builder.build(),
+        DrawMode::Stroke(StrokeMode::new(GRID_COLOR, 1.)),
+        Transform::from_xyz(0., 0., 0.05),
+    );
+    bundle.visibility.is_visible = false;
+    bundle
 }
 
 #[derive(Component)]
@@ -33,17 +92,89 @@ pub(crate) struct MouseCursor;
 pub(crate) struct SelectedTowerProps {
     pub tower: Entity,
     pub dragging: bool,
-    pub hovering_trashcan: bool,
 }
 
 pub(crate) type SelectedTower = Option<SelectedTowerProps>;
 
-pub(crate) fn tower_not_dragging(selected_tower: Res<SelectedTower>) -> ShouldRun {
+/// A tower bought from the palette that is placed where the mouse button is released
+pub(crate) struct PendingPlacement(pub Option<TowerPalette>);
+
+/// Whether the cursor is over the trashcan, which cancels a drag
+pub(crate) struct HoveringTrashcan(pub bool);
+
+/// Shows where the tower being bought or dragged would go, with its range and whether it can
+/// be placed there
+#[derive(Component, PartialEq)]
+struct PlacementPreview {
+    tower: TowerPalette,
+    /// The tower being dragged, or None for a purchase
+    moving: Option<Entity>,
+}
+
+#[derive(Component)]
+struct PlacementGhost;
+
+/// Text telling why the tower can't be placed
+#[derive(Component)]
+struct PlacementReason;
+
+#[derive(Component)]
+struct PlacementGrid;
+
+/// Spacing of the grid the towers snap to, which is centered at the origin
+const GRID_SIZE: f32 = 32.;
+const GRID_COLOR: Color = Color::rgba(1., 1., 1., 0.1);
+const VALID_PLACEMENT_COLOR: Color = Color::rgba(0.5, 1., 0.5, 0.6);
+const INVALID_PLACEMENT_COLOR: Color = Color::rgba(1., 0.4, 0.4, 0.6);
+const PLACEMENT_RANGE_COLOR: Color = Color::rgba(0.8, 0.8, 0.8, 0.5);
+const PLACEMENT_REASON_FONT_SIZE: f32 = 16.;
+
+/// Where a tower dropped at the cursor would go
+fn placement_position(settings: &Settings, mouse_screen: Vec2) -> Vec2 {
+    if settings.snap_to_grid {
+        (mouse_screen / GRID_SIZE).round() * GRID_SIZE
+    } else {
+        mouse_screen
+    }
+}
+
+const SELECTION_BOX_COLOR: Color = Color::rgba(0.5, 1., 0.5, 0.2);
+const SELECTION_MARKER_COLOR: Color = Color::rgb(0.5, 1., 0.5);
+/// Distance from the cursor to a tower center to count as pointing at the tower
+const HOVER_DISTANCE: f32 = 30.;
+
+/// Towers selected with shift-click, box selection or the tower list, as opposed to the tower
+/// under the cursor in `SelectedTower`.
+#[derive(Default)]
+pub(crate) struct TowerSelection(pub Vec<Entity>);
+
+impl TowerSelection {
+    pub(crate) fn toggle(&mut self, tower: Entity) {
+        if let Some(index) = self.0.iter().position(|selected| *selected == tower) {
+            self.0.remove(index);
+        } else {
+            self.0.push(tower);
+        }
+    }
+}
+
+#[derive(Component)]
+struct SelectionBox;
+
+/// A highlight following a selected tower
+#[derive(Component)]
+struct SelectionMarker(Entity);
+
+pub(crate) fn tower_not_dragging(
+    selected_tower: Res<SelectedTower>,
+    pending: Res<PendingPlacement>,
+) -> ShouldRun {
     if selected_tower
         .as_ref()
         .as_ref()
         .map(|f| f.dragging)
         .unwrap_or(false)
+        || pending.0.is_some()
     {
         ShouldRun::No
     } else {
@@ -52,84 +183,73 @@ pub(crate) fn tower_not_dragging(selected_tower: Res<SelectedTower>) -> ShouldRu
 }
 
 fn mouse_system(
-    mut commands: Commands,
     windows: Res<Windows>,
+    arena: Res<ArenaBounds>,
+    settings: Res<Settings>,
     mut query: Query<(&mut Transform, &mut Visibility), With<MouseCursor>>,
-    mut query_towers: Query<(Entity, &mut Position, &Tower)>,
-    query_tower_health: Query<&Tower>,
+    query_towers: Query<(Entity, &Position), With<Tower>>,
     btn: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
     mut selected_tower: ResMut<SelectedTower>,
+    mut pending: ResMut<PendingPlacement>,
+    hovering_trashcan: Res<HoveringTrashcan>,
+    mut command_writer: EventWriter<PlayerCommand>,
+    playback: Res<Playback>,
+    query_aim: Query<&AimMode>,
 ) {
+    // The towers are moved by the replay while watching one
+    if playback.is_playing() {
+        return;
+    }
+    // Shift-click selects towers instead of dragging them
+    let drag_pressed = btn.just_pressed(MouseButton::Left) && !shift_pressed(&keys);
     let window = if let Some(window) = windows.iter().next() {
         window
     } else {
         return;
     };
-    let mouse = window.cursor_position();
+    let mouse_screen = window
+        .cursor_position()
+        .map(|mouse_position| arena.window_to_world(window, mouse_position));
 
-    if let Some(((mut cursor_transform, mut visibility), mouse_position)) =
-        query.get_single_mut().ok().zip(mouse)
+    if let Some(((mut cursor_transform, mut visibility), mouse_screen)) =
+        query.get_single_mut().ok().zip(mouse_screen)
     {
-        let (width, height) = (window.width(), window.height());
-        let mouse_screen = Vec2::new(
-            mouse_position.x - width / 2.,
-            mouse_position.y - height / 2.,
-        );
-
-        let mut dragging = false;
-
-        if let Some(selected_tower) = selected_tower.as_ref() {
-            if selected_tower.dragging {
-                match (|| -> Result<(), QueryComponentError> {
-                    let my_size = query_towers
-                        .get_component::<Tower>(selected_tower.tower)?
-                        .size;
-
-                    let hit_others = query_towers.iter().any(|(entity, position, tower)| {
-                        selected_tower.tower != entity
-                            && mouse_screen.distance_squared(position.0)
-                                < (tower.size + my_size).powf(2.)
-                    });
-
-                    if !hit_others {
-                        let mut tower =
-                            query_towers.get_component_mut::<Position>(selected_tower.tower)?;
-                        tower.0 = mouse_screen;
+        let dragging = selected_tower
+            .as_ref()
+            .as_ref()
+            .map(|selected_tower| selected_tower.dragging)
+            .unwrap_or(false);
+
+        // The dragged tower stays in place with the cursor on it, and the preview follows the mouse
+        if !dragging {
+            if pending.0.is_none() {
+                for (entity, tower_position) in query_towers.iter() {
+                    if tower_position.0.distance(mouse_screen) < HOVER_DISTANCE {
+                        // Clicking a manually aimed tower fires it instead
+                        let drag_pressed = drag_pressed
+                            && query_aim
+                                .get(entity)
+                                .map_or(true, |aim_mode| *aim_mode != AimMode::Manual);
+                        visibility.is_visible = true;
                         *cursor_transform =
-                            Transform::from_xyz(mouse_screen.x, mouse_screen.y, 0.2)
+                            Transform::from_xyz(tower_position.0.x, tower_position.0.y, 0.2)
                                 .with_scale(Vec3::new(2., 2., 1.));
-                    }
-                    Ok(())
-                })() {
-                    Ok(()) => (),
-                    Err(e) => println!("Query component error! the logic seems wrong! {e:?}"),
-                };
-                dragging = true;
-            }
-        }
 
-        if !dragging {
-            for (entity, tower_position, _) in query_towers.iter() {
-                if tower_position.0.distance(mouse_screen) < 30. {
-                    visibility.is_visible = true;
-                    *cursor_transform =
-                        Transform::from_xyz(tower_position.0.x, tower_position.0.y, 0.2)
-                            .with_scale(Vec3::new(2., 2., 1.));
-
-                    if let Some(selected_tower) = selected_tower.as_mut() {
-                        selected_tower.tower = entity;
-                        if btn.just_pressed(MouseButton::Left) {
-                            selected_tower.dragging = true;
+                        if let Some(selected_tower) = selected_tower.as_mut() {
+                            selected_tower.tower = entity;
+                            if drag_pressed {
+                                selected_tower.dragging = true;
+                            }
+                        } else {
+                            *selected_tower = Some(SelectedTowerProps {
+                                tower: entity,
+                                dragging: drag_pressed,
+                            });
                         }
-                    } else {
-                        *selected_tower = Some(SelectedTowerProps {
-                            tower: entity,
-                            dragging: btn.just_pressed(MouseButton::Left),
-                            hovering_trashcan: false,
-                        });
-                    }
 
-                    return;
+                        return;
+                    }
                 }
             }
 
@@ -137,15 +257,419 @@ fn mouse_system(
         }
     }
     if btn.just_released(MouseButton::Left) {
-        if let Some(selected_tower) = selected_tower.as_ref() {
-            if selected_tower.dragging && selected_tower.hovering_trashcan {
-                if let Ok(tower) = query_tower_health.get(selected_tower.tower) {
-                    commands.entity(tower.health_bar.0).despawn();
-                    commands.entity(tower.health_bar.1).despawn();
-                }
-                commands.entity(selected_tower.tower).despawn_recursive();
+        let position = mouse_screen.map(|mouse_screen| placement_position(&settings, mouse_screen));
+        if let Some(tower) = pending.0.take() {
+            if let (Some(position), false) = (position, hovering_trashcan.0) {
+                command_writer.send(PlayerCommand::PlaceTower { tower, position });
+            }
+        } else if let Some(selected_tower) = selected_tower.as_ref() {
+            if selected_tower.dragging && hovering_trashcan.0 {
+                command_writer.send(PlayerCommand::SellTower {
+                    tower: selected_tower.tower,
+                });
+            } else if let (true, Some(position)) = (selected_tower.dragging, position) {
+                command_writer.send(PlayerCommand::MoveTower {
+                    tower: selected_tower.tower,
+                    position,
+                });
             }
         }
         *selected_tower = None;
     }
 }
+
+fn shift_pressed(keys: &Input<KeyCode>) -> bool {
+    keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift)
+}
+
+/// Shift-click toggles a tower in the selection, and shift-dragging from an empty spot selects
+/// the towers in the box. Escape clears the selection.
+fn selection_system(
+    windows: Res<Windows>,
+    arena: Res<ArenaBounds>,
+    btn: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    mut selection: ResMut<TowerSelection>,
+    mut box_start: Local<Option<Vec2>>,
+    query_towers: Query<(Entity, &Position), With<Tower>>,
+    mut query_box: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<SelectionBox>>,
+) {
+    // Check before borrowing mutably to avoid triggering change detection every frame
+    if selection
+        .0
+        .iter()
+        .any(|tower| query_towers.get(*tower).is_err())
+    {
+        selection.0.retain(|tower| query_towers.get(*tower).is_ok());
+    }
+
+    if settings
+        .key_bindings
+        .just_pressed(&keys, KeyAction::ClearSelection)
+        && !selection.0.is_empty()
+    {
+        selection.0.clear();
+    }
+
+    let mouse_screen = if let Some(mouse_screen) = windows
+        .get_primary()
+        .and_then(|window| Some(arena.window_to_world(window, window.cursor_position()?)))
+    {
+        mouse_screen
+    } else {
+        return;
+    };
+
+    if btn.just_pressed(MouseButton::Left) && shift_pressed(&keys) {
+        if let Some((tower, _)) = query_towers
+            .iter()
+            .find(|(_, position)| position.0.distance(mouse_screen) < HOVER_DISTANCE)
+        {
+            selection.toggle(tower);
+        } else {
+            *box_start = Some(mouse_screen);
+        }
+    }
+
+    let (mut transform, mut sprite, mut visibility) =
+        if let Ok(selection_box) = query_box.get_single_mut() {
+            selection_box
+        } else {
+            return;
+        };
+
+    if let Some(start) = *box_start {
+        let min = start.min(mouse_screen);
+        let max = start.max(mouse_screen);
+        if btn.just_released(MouseButton::Left) {
+            for (tower, position) in query_towers.iter() {
+                if min.cmple(position.0).all()
+                    && position.0.cmple(max).all()
+                    && !selection.0.contains(&tower)
+                {
+                    selection.0.push(tower);
+                }
+            }
+            *box_start = None;
+            visibility.is_visible = false;
+        } else {
+            let center = (min + max) / 2.;
+            *transform = Transform::from_xyz(center.x, center.y, 0.3);
+            sprite.custom_size = Some(max - min);
+            visibility.is_visible = true;
+        }
+    }
+}
+
+fn selection_marker_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    selection: Res<TowerSelection>,
+    mut query_markers: Query<(Entity, &SelectionMarker, &mut Transform)>,
+    query_towers: Query<&Position, With<Tower>>,
+) {
+    let mut marked = vec![];
+    for (entity, marker, mut transform) in query_markers.iter_mut() {
+        match query_towers.get(marker.0) {
+            Ok(position) if selection.0.contains(&marker.0) => {
+                transform.translation = position.0.extend(0.15);
+                marked.push(marker.0);
+            }
+            _ => commands.entity(entity).despawn(),
+        }
+    }
+
+    for tower in selection.0.iter().filter(|tower| !marked.contains(tower)) {
+        if let Ok(position) = query_towers.get(*tower) {
+            commands
+                .spawn_bundle(SpriteBundle {
+                    texture: asset_server.load("select-marker.png"),
+                    sprite: Sprite {
+                        color: SELECTION_MARKER_COLOR,
+                        ..default()
+                    },
+                    transform: Transform::from_translation(position.0.extend(0.15))
+                        .with_scale(Vec3::new(2., 2., 1.)),
+                    ..default()
+                })
+                .insert(SelectionMarker(*tower));
+        }
+    }
+}
+
+/// Show the preview of the tower being bought or dragged, and the grid if snapping.
+fn placement_preview_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    windows: Res<Windows>,
+    arena: Res<ArenaBounds>,
+    current_map: Res<CurrentMap>,
+    settings: Res<Settings>,
+    pending: Res<PendingPlacement>,
+    selected_tower: Res<SelectedTower>,
+    hovering_trashcan: Res<HoveringTrashcan>,
+    query_towers: Query<(Entity, &Position, &Tower, &TowerLevel)>,
+    query_type: TowerTypeQuery,
+    mut query_preview: Query<(Entity, &PlacementPreview, &mut Transform)>,
+    mut query_ghost: Query<&mut Sprite, With<PlacementGhost>>,
+    mut query_reason: Query<&mut Text, With<PlacementReason>>,
+    mut query_grid: Query<&mut Visibility, With<PlacementGrid>>,
+) {
+    let mouse_screen = windows
+        .get_primary()
+        .and_then(|window| Some(arena.window_to_world(window, window.cursor_position()?)));
+
+    let dragged = selected_tower
+        .as_ref()
+        .as_ref()
+        .filter(|selected_tower| selected_tower.dragging)
+        .and_then(|selected_tower| {
+            let tower = TowerPalette::of_tower(&query_type, selected_tower.tower)?;
+            Some((tower, Some(selected_tower.tower)))
+        });
+    let placing = pending.0.map(|tower| (tower, None)).or(dragged);
+
+    for mut visibility in query_grid.iter_mut() {
+        let visible = settings.snap_to_grid && placing.is_some();
+        if visibility.is_visible != visible {
+            visibility.is_visible = visible;
+        }
+    }
+
+    let (preview, mouse_screen) = match (placing, mouse_screen) {
+        (Some((tower, moving)), Some(mouse_screen)) => {
+            (PlacementPreview { tower, moving }, mouse_screen)
+        }
+        _ => {
+            for (entity, ..) in query_preview.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            return;
+        }
+    };
+
+    let position = placement_position(&settings, mouse_screen);
+    let (size, level) = preview
+        .moving
+        .and_then(|moving| query_towers.get(moving).ok())
+        .map(|(_, _, tower, level)| (tower.size, level.level))
+        .unwrap_or((preview.tower.size(), 0));
+    let others = query_towers
+        .iter()
+        .filter(|(entity, ..)| Some(*entity) != preview.moving)
+        .map(|(_, position, tower, _)| (position.0, tower.size));
+    // Releasing over the trashcan doesn't place the tower
+    let error = if hovering_trashcan.0 {
+        None
+    } else {
+        placement_error(&arena, &current_map, others, position, size)
+    };
+
+    let mut found = false;
+    for (entity, existing, mut transform) in query_preview.iter_mut() {
+        if *existing == preview && !found {
+            transform.translation = position.extend(0.2);
+            found = true;
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    if !found {
+        let range = preview.tower.range(level);
+        let icon = preview.tower.icon();
+        commands
+            .spawn_bundle(TransformBundle::from_transform(
+                Transform::from_translation(position.extend(0.2)),
+            ))
+            .insert(preview)
+            .with_children(|parent| {
+                parent
+                    .spawn_bundle(SpriteBundle {
+                        texture: asset_server.load(icon),
+                        transform: Transform::from_scale(Vec3::new(3., 3., 1.)),
+                        ..default()
+                    })
+                    .insert(PlacementGhost);
+
+                if let Some(range) = range {
+                    parent.spawn_bundle(GeometryBuilder::build_as(
+                        &Circle {
+                            radius: range,
+                            center: Vec2::ZERO,
+                        },
+                        DrawMode::Stroke(StrokeMode::new(PLACEMENT_RANGE_COLOR, 1.)),
+                        Transform::from_xyz(0., 0., -0.1),
+                    ));
+                }
+
+                parent
+                    .spawn_bundle(Text2dBundle {
+                        text: Text::with_section(
+                            "",
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: PLACEMENT_REASON_FONT_SIZE,
+                                color: INVALID_PLACEMENT_COLOR,
+                            },
+                            TextAlignment {
+                                vertical: VerticalAlign::Center,
+                                horizontal: HorizontalAlign::Center,
+                            },
+                        ),
+                        transform: Transform::from_xyz(0., -size - PLACEMENT_REASON_FONT_SIZE, 0.1),
+                        ..default()
+                    })
+                    .insert(PlacementReason);
+            });
+    }
+
+    // Newly spawned parts are updated from the next frame
+    for mut sprite in query_ghost.iter_mut() {
+        sprite.color = if error.is_some() {
+            INVALID_PLACEMENT_COLOR
+        } else {
+            VALID_PLACEMENT_COLOR
+        };
+    }
+    for mut text in query_reason.iter_mut() {
+        let reason = error.unwrap_or("");
+        if text.sections[0].value != reason {
+            text.sections[0].value = reason.to_string();
+        }
+    }
+}
+
+/// Cycle the targeting of the selected towers, or the tower under the cursor if none is
+/// selected.
+fn targeting_key_system(
+    keys: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    selected_tower: Res<SelectedTower>,
+    selection: Res<TowerSelection>,
+    query_targeting: Query<&Targeting>,
+    mut command_writer: EventWriter<PlayerCommand>,
+    playback: Res<Playback>,
+) {
+    if playback.is_playing() {
+        return;
+    }
+    if !settings
+        .key_bindings
+        .just_pressed(&keys, KeyAction::CycleTargeting)
+    {
+        return;
+    }
+    for tower in shown_towers(&selected_tower, &selection) {
+        if let Ok(targeting) = query_targeting.get(tower) {
+            command_writer.send(PlayerCommand::SetTargeting {
+                tower,
+                targeting: targeting.next(),
+            });
+        }
+    }
+}
+
+/// The selected towers, or the tower under the cursor if none is selected
+fn shown_towers(selected_tower: &SelectedTower, selection: &TowerSelection) -> Vec<Entity> {
+    if selection.0.is_empty() {
+        selected_tower.iter().map(|props| props.tower).collect()
+    } else {
+        selection.0.clone()
+    }
+}
+
+/// Toggle holding the direction of the cursor on the shown towers, and the manual aim of a
+/// single tower.
+fn aim_key_system(
+    windows: Res<Windows>,
+    arena: Res<ArenaBounds>,
+    keys: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    selected_tower: Res<SelectedTower>,
+    selection: Res<TowerSelection>,
+    query_aim: Query<(Entity, &Position, &AimMode)>,
+    mut command_writer: EventWriter<PlayerCommand>,
+    playback: Res<Playback>,
+) {
+    if playback.is_playing() {
+        return;
+    }
+    if settings
+        .key_bindings
+        .just_pressed(&keys, KeyAction::HoldDirection)
+    {
+        let mouse_screen = windows
+            .get_primary()
+            .and_then(|window| Some(arena.window_to_world(window, window.cursor_position()?)));
+        for tower in shown_towers(&selected_tower, &selection) {
+            let (position, aim_mode) = if let Ok((_, position, aim_mode)) = query_aim.get(tower) {
+                (position, aim_mode)
+            } else {
+                continue;
+            };
+            let mode = match (aim_mode, mouse_screen) {
+                (AimMode::Hold { .. }, _) => AimMode::Auto,
+                (_, Some(mouse_screen)) => AimMode::Hold {
+                    direction: angle_to(position, &Position(mouse_screen)),
+                    arc: DEFAULT_HOLD_ARC,
+                },
+                _ => continue,
+            };
+            command_writer.send(PlayerCommand::SetAimMode { tower, mode });
+        }
+    }
+
+    if settings
+        .key_bindings
+        .just_pressed(&keys, KeyAction::ManualAim)
+    {
+        // Pressing the key again gives the control back to the tower
+        if let Some((tower, ..)) = query_aim
+            .iter()
+            .find(|(_, _, aim_mode)| **aim_mode == AimMode::Manual)
+        {
+            command_writer.send(PlayerCommand::SetAimMode {
+                tower,
+                mode: AimMode::Auto,
+            });
+        } else if let [tower] = shown_towers(&selected_tower, &selection)[..] {
+            command_writer.send(PlayerCommand::SetAimMode {
+                tower,
+                mode: AimMode::Manual,
+            });
+        }
+    }
+}
+
+/// Widen or narrow the firing arc of the shown towers holding a direction.
+fn hold_arc_wheel_system(
+    mut wheel_reader: EventReader<MouseWheel>,
+    selected_tower: Res<SelectedTower>,
+    selection: Res<TowerSelection>,
+    query_aim: Query<&AimMode>,
+    mut command_writer: EventWriter<PlayerCommand>,
+    playback: Res<Playback>,
+) {
+    const ARC_STEP: f64 = std::f64::consts::PI / 12.;
+
+    let steps: f32 = wheel_reader.iter().map(|wheel| wheel.y.signum()).sum();
+    if steps == 0. || playback.is_playing() {
+        return;
+    }
+    for tower in shown_towers(&selected_tower, &selection) {
+        if let Ok(AimMode::Hold { direction, arc }) = query_aim.get(tower) {
+            let new_arc = (arc + steps as f64 * ARC_STEP).clamp(MIN_HOLD_ARC, MAX_HOLD_ARC);
+            if new_arc != *arc {
+                command_writer.send(PlayerCommand::SetAimMode {
+                    tower,
+                    mode: AimMode::Hold {
+                        direction: *direction,
+                        arc: new_arc,
+                    },
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! Recording of the player commands in a stage and playing them back.
+//!
+//! A replay has the towers at the start of the stage, the seed of its random numbers and the
+//! commands with the simulation step they were applied at. The simulation steps the same way
+//! from the same seed, so applying the commands at their steps plays out the stage again.
+//! The playback can be sped up, and clicking the progress bar seeks to a time in the stage.
+
+use crate::{
+    arena::ArenaBounds,
+    command::{apply_player_commands, despawn_tower, PlayerCommand},
+    map::CurrentMap,
+    save::{load_game, load_replay, restore_towers, save_replay, snapshot_towers, SavedTowerQuery},
+    settings::{KeyAction, Settings},
+    simulation::{SimClock, SimulationStage},
+    tower::{AimMode, Targeting, Tower},
+    ui::{not_paused, PauseEvent, PauseState, TowerPalette},
+    ClearEvent, Level, Position, Scoreboard, StageClear,
+};
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+use serde::{Deserialize, Serialize};
+
+/// A tower has to be within this distance from the recorded position to receive a command
+const MATCH_DISTANCE: f32 = 1.;
+/// The speed key doubles the playback speed up to this, and then goes back to the normal speed
+const MAX_PLAYBACK_SPEED: f64 = 8.;
+
+pub(crate) struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ReplayEvent>();
+        app.add_event::<WatchReplayEvent>();
+        app.add_event::<SeekReplayEvent>();
+        app.insert_resource(Recording(None));
+        app.insert_resource(Playback(None));
+        app.add_system_to_stage(
+            SimulationStage,
+            start_recording_system.exclusive_system().at_start(),
+        );
+        app.add_system(record_system.after(apply_player_commands));
+        app.add_system(watch_replay_system);
+        app.add_system(playback_system.before(apply_player_commands));
+        app.add_system(playback_speed_key_system);
+        app.add_system(end_playback_system.after(crate::reset_game));
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub(crate) enum ReplayCommand {
+    Purchase {
+        tower: TowerPalette,
+        position: Vec2,
+    },
+    Move {
+        from: Vec2,
+        to: Vec2,
+    },
+    Trash {
+        position: Vec2,
+    },
+    Pause,
+    Quit,
+    SetTargeting {
+        position: Vec2,
+        targeting: Targeting,
+    },
+    SetAimMode {
+        position: Vec2,
+        mode: AimMode,
+    },
+    Undo,
+}
+
+/// Sent for each player command that has been applied
+pub(crate) struct ReplayEvent(pub ReplayCommand);
+
+/// Start playing back the last recorded replay
+pub(crate) struct WatchReplayEvent;
+
+/// Seek the playback to the seconds since the stage started
+pub(crate) struct SeekReplayEvent(pub f32);
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Replay {
+    difficulty: usize,
+    map: usize,
+    credits: f64,
+    /// The towers at the start of the stage in the save format
+    towers: serde_json::Value,
+    /// Missing in the replays recorded before the seeded simulation
+    #[serde(default)]
+    seed: Option<u64>,
+    /// Commands with the simulation step since the stage started
+    commands: Vec<(u64, ReplayCommand)>,
+}
+
+impl Replay {
+    fn command_step(&self, index: usize) -> Option<u64> {
+        self.commands.get(index).map(|(step, _)| *step)
+    }
+}
+
+struct Recording(Option<Replay>);
+
+struct PlaybackState {
+    replay: Replay,
+    next: usize,
+}
+
+pub(crate) struct Playback(Option<PlaybackState>);
+
+impl Playback {
+    pub(crate) fn is_playing(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+/// Take the towers at the first step of the stage, after the commands of the build phase.
+fn start_recording_system(
+    level: Res<Level>,
+    clock: Res<SimClock>,
+    current_map: Res<CurrentMap>,
+    scoreboard: Res<Scoreboard>,
+    playback: Res<Playback>,
+    query_towers: SavedTowerQuery,
+    mut recording: ResMut<Recording>,
+) {
+    if !clock.is_running() || clock.step != 0 || playback.is_playing() {
+        return;
+    }
+    if let Level::Running { difficulty, .. } = level.as_ref() {
+        recording.0 = Some(Replay {
+            difficulty: *difficulty,
+            map: current_map.0,
+            credits: scoreboard.credits,
+            towers: snapshot_towers(&query_towers),
+            seed: Some(clock.seed),
+            commands: vec![],
+        });
+    }
+}
+
+fn record_system(
+    clock: Res<SimClock>,
+    mut recording: ResMut<Recording>,
+    mut reader: EventReader<ReplayEvent>,
+    mut clear_reader: EventReader<ClearEvent>,
+) {
+    for ReplayEvent(command) in reader.iter() {
+        if let Some(replay) = recording.0.as_mut() {
+            replay.commands.push((clock.step, *command));
+        }
+    }
+
+    if clear_reader.iter().next().is_some() {
+        if let Some(replay) = recording.0.take() {
+            save_replay(&replay);
+        }
+    }
+}
+
+/// Start playing back the last replay, or seek in the one being played back. Seeking back starts
+/// the playback over and runs up to the time.
+fn watch_replay_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut reader: EventReader<WatchReplayEvent>,
+    mut seek_reader: EventReader<SeekReplayEvent>,
+    query: Query<Entity, With<StageClear>>,
+    query_towers: Query<(Entity, &Tower)>,
+    pause_state: Res<PauseState>,
+    mut pause_writer: EventWriter<PauseEvent>,
+    mut level: ResMut<Level>,
+    mut current_map: ResMut<CurrentMap>,
+    mut arena: ResMut<ArenaBounds>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut clock: ResMut<SimClock>,
+    mut playback: ResMut<Playback>,
+) {
+    let watch = reader.iter().last().is_some();
+    let seek = seek_reader
+        .iter()
+        .last()
+        .map(|SeekReplayEvent(seconds)| SimClock::step_at(*seconds));
+    let replay = match playback.0.take() {
+        Some(state) if matches!(seek, Some(seek) if seek < clock.step) => state.replay,
+        Some(state) => {
+            // Seeking forward only runs the steps up to the time
+            if seek.is_some() {
+                clock.seek = seek;
+            }
+            playback.0 = Some(state);
+            return;
+        }
+        None if watch && !level.can_build() => {
+            if let Some(replay) = load_replay() {
+                replay
+            } else {
+                println!("No replay to watch");
+                return;
+            }
+        }
+        None => return,
+    };
+
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    for (entity, tower) in query_towers.iter() {
+        despawn_tower(&mut commands, entity, tower);
+    }
+    restore_towers(&mut commands, &asset_server, replay.towers.clone());
+
+    current_map.0 = replay.map;
+    // The map would only update the arena after the first steps
+    *arena = current_map.spec().arena;
+    scoreboard.credits = replay.credits;
+    scoreboard.score = 0.;
+    *level = Level::start(replay.difficulty);
+    if not_paused(pause_state) == ShouldRun::No {
+        pause_writer.send(PauseEvent);
+    }
+
+    clock.restart();
+    clock.next_seed = replay.seed;
+    clock.stop_at = replay.command_step(0);
+    clock.seek = seek;
+    playback.0 = Some(PlaybackState { replay, next: 0 });
+}
+
+fn nearest_tower(
+    query_towers: &Query<(Entity, &Position), With<Tower>>,
+    position: Vec2,
+) -> Option<Entity> {
+    query_towers
+        .iter()
+        .find(|(_, tower_position)| tower_position.0.distance(position) < MATCH_DISTANCE)
+        .map(|(entity, _)| entity)
+}
+
+/// Send the recorded commands as player commands, so that they are validated the same way.
+fn playback_system(
+    mut clock: ResMut<SimClock>,
+    mut playback: ResMut<Playback>,
+    query_towers: Query<(Entity, &Position), With<Tower>>,
+    mut command_writer: EventWriter<PlayerCommand>,
+) {
+    let state = if let (Some(state), true) = (playback.0.as_mut(), clock.is_running()) {
+        state
+    } else {
+        return;
+    };
+
+    while let Some((command_step, command)) = state.replay.commands.get(state.next) {
+        if clock.step < *command_step {
+            break;
+        }
+        state.next += 1;
+
+        let player_command = match *command {
+            ReplayCommand::Purchase { tower, position } => {
+                Some(PlayerCommand::PlaceTower { tower, position })
+            }
+            ReplayCommand::Move { from, to } => {
+                nearest_tower(&query_towers, from).map(|tower| PlayerCommand::MoveTower {
+                    tower,
+                    position: to,
+                })
+            }
+            ReplayCommand::Trash { position } => nearest_tower(&query_towers, position)
+                .map(|tower| PlayerCommand::SellTower { tower }),
+            ReplayCommand::Pause => Some(PlayerCommand::Pause),
+            ReplayCommand::Quit => Some(PlayerCommand::Quit),
+            ReplayCommand::SetTargeting {
+                position,
+                targeting,
+            } => nearest_tower(&query_towers, position)
+                .map(|tower| PlayerCommand::SetTargeting { tower, targeting }),
+            // The cursor isn't recorded, so a manually aimed tower aims by itself in the replay
+            ReplayCommand::SetAimMode { position, mode } => nearest_tower(&query_towers, position)
+                .map(|tower| PlayerCommand::SetAimMode {
+                    tower,
+                    mode: if mode == AimMode::Manual {
+                        AimMode::Auto
+                    } else {
+                        mode
+                    },
+                }),
+            ReplayCommand::Undo => Some(PlayerCommand::Undo),
+        };
+        if let Some(player_command) = player_command {
+            command_writer.send(player_command);
+        }
+    }
+    clock.stop_at = state.replay.command_step(state.next);
+}
+
+/// Double the playback speed, or go back to the normal speed from the fastest.
+fn playback_speed_key_system(
+    keys: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    playback: Res<Playback>,
+    mut clock: ResMut<SimClock>,
+) {
+    if playback.is_playing()
+        && settings
+            .key_bindings
+            .just_pressed(&keys, KeyAction::PlaybackSpeed)
+    {
+        clock.speed = if MAX_PLAYBACK_SPEED <= clock.speed {
+            1.
+        } else {
+            clock.speed * 2.
+        };
+        println!("Playback speed: {}x", clock.speed);
+    }
+}
+
+/// Bring back the towers and the progress from the save after watching a replay.
+fn end_playback_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut reader: EventReader<ClearEvent>,
+    query_towers: Query<(Entity, &Tower)>,
+    mut scoreboard: ResMut<Scoreboard>,
+    mut clock: ResMut<SimClock>,
+    mut playback: ResMut<Playback>,
+) {
+    if reader.iter().next().is_none() || !playback.is_playing() {
+        return;
+    }
+    for (entity, tower) in query_towers.iter() {
+        despawn_tower(&mut commands, entity, tower);
+    }
+    load_game(&mut commands, &asset_server, &mut scoreboard);
+    clock.speed = 1.;
+    clock.stop_at = None;
+    playback.0 = None;
+}
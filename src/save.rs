@@ -1,7 +1,11 @@
 use crate::{
+    replay::Replay,
+    settings::Settings,
     tower::{
-        spawn_beam_tower, spawn_healer, spawn_missile_tower, spawn_shotgun, spawn_turret,
-        BeamTower, Healer, MissileShooter, Shotgun, Tower, TowerInitBundle, TowerLevel, TowerScore,
+        spawn_amplifier, spawn_beam_tower, spawn_cryo_tower, spawn_healer, spawn_mine_layer,
+        spawn_missile_tower, spawn_shotgun, spawn_turret, AimMode, Amplifier, BeamTower, CryoTower,
+        Healer, MineLayer, MissileShooter, Shotgun, Targeting, Tower, TowerInitBundle, TowerLevel,
+        TowerScore,
     },
     Health, Position, Rotation, Scoreboard, MAX_DIFFICULTY,
 };
@@ -10,6 +14,11 @@ use serde_json::{from_str, from_value, json, Value};
 
 #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
 const WASM_SAVE_KEY: &str = "turret-rs/save";
+/// Kept apart from the save so that resetting the progress doesn't reset the settings
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+const WASM_SETTINGS_KEY: &str = "turret-rs/settings";
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+const WASM_REPLAY_KEY: &str = "turret-rs/replay";
 
 pub(crate) struct SaveGameEvent;
 
@@ -22,38 +31,54 @@ impl<T: std::error::Error> From<T> for MyError {
     }
 }
 
+pub(crate) type SavedTowerQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Position,
+        &'static Rotation,
+        &'static TowerScore,
+        &'static TowerLevel,
+        &'static Health,
+        &'static AimMode,
+        &'static Targeting,
+        Option<&'static Shotgun>,
+        Option<&'static Healer>,
+        Option<&'static MissileShooter>,
+        Option<&'static BeamTower>,
+        Option<&'static CryoTower>,
+        Option<&'static MineLayer>,
+        Option<&'static Amplifier>,
+    ),
+    With<Tower>,
+>;
+
+fn towers_to_json(query: &SavedTowerQuery) -> Result<Value, MyError> {
+    query.iter().map(|(position, rotation, tower_score, tower_level, health, aim_mode, targeting, shotgun, healer, missile_tower, beam_tower, cryo_tower, mine_layer, amplifier)| -> Result<serde_json::Value, MyError>{
+        Ok(json!({
+            "type": if shotgun.is_some() { "Shotgun" } else if healer.is_some() { "Healer" } else if missile_tower.is_some() { "MissileTower" } else if beam_tower.is_some() { "BeamTower" } else if cryo_tower.is_some() { "CryoTower" } else if mine_layer.is_some() { "MineLayer" } else if amplifier.is_some() { "Amplifier" } else { "Turret"},
+            "tower_score": tower_score,
+            "tower_level": tower_level,
+            "position": position,
+            "rotation": rotation,
+            "health": health,
+            // Manual aim only lasts while the player is controlling the tower
+            "aim_mode": if *aim_mode == AimMode::Manual { AimMode::Auto } else { *aim_mode },
+            "targeting": targeting,
+        }))
+    }).collect()
+}
+
 pub(crate) fn save_game(
     mut reader: EventReader<SaveGameEvent>,
-    query: Query<
-        (
-            &Position,
-            &Rotation,
-            &TowerScore,
-            &TowerLevel,
-            &Health,
-            Option<&Shotgun>,
-            Option<&Healer>,
-            Option<&MissileShooter>,
-            Option<&BeamTower>,
-        ),
-        With<Tower>,
-    >,
+    query: SavedTowerQuery,
     scoreboard: Res<Scoreboard>,
 ) {
     for _e in reader.iter() {
         println!("Save event");
 
         match (|| -> Result<(), MyError> {
-            let json_towers = query.iter().map(|(position, rotation, tower_score, tower_level, health, shotgun, healer, missile_tower, beam_tower)| -> Result<serde_json::Value, MyError>{
-                Ok(json!({
-                    "type": if shotgun.is_some() { "Shotgun" } else if healer.is_some() { "Healer" } else if missile_tower.is_some() { "MissileTower" } else if beam_tower.is_some() { "BeamTower" } else { "Turret"},
-                    "tower_score": tower_score,
-                    "tower_level": tower_level,
-                    "position": position,
-                    "rotation": rotation,
-                    "health": health,
-                }))
-            }).collect::<Result<serde_json::Value, MyError>>()?;
+            let json_towers = towers_to_json(&query)?;
 
             let json_container = json!({
                 "scoreboard": &*scoreboard,
@@ -61,16 +86,10 @@ pub(crate) fn save_game(
             });
 
             #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
-            std::fs::write("save.json", serde_json::to_string(&json_container)?)?;
+            write_storage("save.json", &serde_json::to_string(&json_container)?)?;
 
             #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
-            {
-                let local_storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
-
-                local_storage
-                    .set_item(WASM_SAVE_KEY, &serde_json::to_string(&json_container)?)
-                    .unwrap();
-            }
+            write_storage(WASM_SAVE_KEY, &serde_json::to_string(&json_container)?)?;
 
             Ok(())
         })() {
@@ -80,6 +99,116 @@ pub(crate) fn save_game(
     }
 }
 
+/// Write to a file on native, or to the local storage under the key on the web.
+fn write_storage(key: &str, contents: &str) -> Result<(), MyError> {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    std::fs::write(key, contents)?;
+
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    {
+        let local_storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
+
+        local_storage.set_item(key, contents).unwrap();
+    }
+
+    Ok(())
+}
+
+fn read_storage(key: &str) -> Option<String> {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    return std::fs::read_to_string(key).ok();
+
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    {
+        let local_storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
+
+        local_storage.get_item(key).unwrap()
+    }
+}
+
+pub(crate) fn save_settings(settings: &Settings) {
+    match (|| -> Result<(), MyError> {
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        write_storage("settings.json", &serde_json::to_string(settings)?)?;
+
+        #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+        write_storage(WASM_SETTINGS_KEY, &serde_json::to_string(settings)?)?;
+
+        Ok(())
+    })() {
+        Ok(()) => println!("Settings saved"),
+        Err(e) => println!("Saving settings failed!: {e:?}"),
+    }
+}
+
+pub(crate) fn load_settings() -> Option<Settings> {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    let json_str = read_storage("settings.json")?;
+
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    let json_str = read_storage(WASM_SETTINGS_KEY)?;
+
+    match from_str(&json_str) {
+        Ok(settings) => Some(settings),
+        Err(e) => {
+            println!("Load settings error: {e:?}");
+            None
+        }
+    }
+}
+
+/// The towers in the save format, for a replay to start from the same layout.
+pub(crate) fn snapshot_towers(query: &SavedTowerQuery) -> Value {
+    towers_to_json(query).unwrap_or_else(|e| {
+        println!("Snapshot failed!: {e:?}");
+        Value::Null
+    })
+}
+
+/// Spawn the towers taken by `snapshot_towers`.
+pub(crate) fn restore_towers(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    towers: Value,
+) {
+    if let Value::Array(arr) = towers {
+        if let Err(e) = spawn_saved_towers(commands, asset_server, arr) {
+            println!("Restoring towers failed!: {e:?}");
+        }
+    }
+}
+
+pub(crate) fn save_replay(replay: &Replay) {
+    match (|| -> Result<(), MyError> {
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        write_storage("replay.json", &serde_json::to_string(replay)?)?;
+
+        #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+        write_storage(WASM_REPLAY_KEY, &serde_json::to_string(replay)?)?;
+
+        Ok(())
+    })() {
+        Ok(()) => println!("Replay saved"),
+        Err(e) => println!("Saving replay failed!: {e:?}"),
+    }
+}
+
+pub(crate) fn load_replay() -> Option<Replay> {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    let json_str = read_storage("replay.json")?;
+
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    let json_str = read_storage(WASM_REPLAY_KEY)?;
+
+    match from_str(&json_str) {
+        Ok(replay) => Some(replay),
+        Err(e) => {
+            println!("Load replay error: {e:?}");
+            None
+        }
+    }
+}
+
 macro_rules! _unwrap_or_continue {
     {$e:expr} => {
         if let Some(e) = $e {
@@ -107,24 +236,18 @@ pub(crate) fn load_game(
     scoreboard: &mut Scoreboard,
 ) {
     #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
-    let json_str = if let Ok(json_str) = std::fs::read_to_string("save.json") {
+    let json_str = read_storage("save.json");
+
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    let json_str = read_storage(WASM_SAVE_KEY);
+
+    let json_str = if let Some(json_str) = json_str {
         json_str
     } else {
         println!("Save file was not found!");
         return;
     };
 
-    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
-    let json_str = {
-        let local_storage = web_sys::window().unwrap().local_storage().unwrap().unwrap();
-
-        if let Some(value) = local_storage.get_item(WASM_SAVE_KEY).unwrap() {
-            value
-        } else {
-            return;
-        }
-    };
-
     match (|| -> Result<(), MyError> {
         let mut json_container: Value = from_str(&json_str)?;
 
@@ -141,74 +264,7 @@ pub(crate) fn load_game(
         }
 
         if let Some(Value::Array(arr)) = json_container.get_mut("towers").map(|t| t.take()) {
-            for mut tower in arr {
-                let position = take_or_continue!(tower, "position");
-                let rotation = take_or_continue!(tower, "rotation");
-                let health = take_or_continue!(tower, "health");
-                let tower_score = take_or_continue!(tower, "tower_score");
-                let tower_level = take_or_continue!(tower, "tower_level");
-                let tower_type = if let Some(Value::String(s)) = tower.get("type") {
-                    s
-                } else {
-                    println!("No type defined");
-                    continue;
-                };
-
-                let bundle = TowerInitBundle {
-                    health: Some(serde_json::from_value(health)?),
-                    tower_score: Some(serde_json::from_value(tower_score)?),
-                    tower_level: Some(serde_json::from_value(tower_level)?),
-                };
-
-                match tower_type as _ {
-                    "Turret" => {
-                        spawn_turret(
-                            commands,
-                            asset_server,
-                            serde_json::from_value(position)?,
-                            serde_json::from_value(rotation)?,
-                            bundle,
-                        );
-                    }
-                    "Shotgun" => {
-                        spawn_shotgun(
-                            commands,
-                            asset_server,
-                            serde_json::from_value(position)?,
-                            serde_json::from_value(rotation)?,
-                            bundle,
-                        );
-                    }
-                    "Healer" => {
-                        spawn_healer(
-                            commands,
-                            asset_server,
-                            serde_json::from_value(position)?,
-                            serde_json::from_value(rotation)?,
-                            bundle,
-                        );
-                    }
-                    "MissileTower" => {
-                        spawn_missile_tower(
-                            commands,
-                            asset_server,
-                            serde_json::from_value(position)?,
-                            serde_json::from_value(rotation)?,
-                            bundle,
-                        );
-                    }
-                    "BeamTower" => {
-                        spawn_beam_tower(
-                            commands,
-                            asset_server,
-                            serde_json::from_value(position)?,
-                            serde_json::from_value(rotation)?,
-                            bundle,
-                        );
-                    }
-                    _ => println!("Unrecognized type!"),
-                }
-            }
+            spawn_saved_towers(commands, asset_server, arr)?;
         }
         Ok(())
     })() {
@@ -216,3 +272,111 @@ pub(crate) fn load_game(
         Err(e) => println!("Load error: {e:?}"),
     }
 }
+
+fn spawn_saved_towers(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    arr: Vec<Value>,
+) -> Result<(), MyError> {
+    for mut tower in arr {
+        let position = take_or_continue!(tower, "position");
+        let rotation = take_or_continue!(tower, "rotation");
+        let health = take_or_continue!(tower, "health");
+        let tower_score = take_or_continue!(tower, "tower_score");
+        let tower_level = take_or_continue!(tower, "tower_level");
+        // Missing in the saves before the targeting and aim modes
+        let targeting = tower.get_mut("targeting").map(|p| p.take());
+        let aim_mode = tower.get_mut("aim_mode").map(|p| p.take());
+        let tower_type = if let Some(Value::String(s)) = tower.get("type") {
+            s
+        } else {
+            println!("No type defined");
+            continue;
+        };
+
+        let bundle = TowerInitBundle {
+            health: Some(serde_json::from_value(health)?),
+            tower_score: Some(serde_json::from_value(tower_score)?),
+            tower_level: Some(serde_json::from_value(tower_level)?),
+            targeting: targeting.map(serde_json::from_value).transpose()?,
+            aim_mode: aim_mode.map(serde_json::from_value).transpose()?,
+        };
+
+        match tower_type as _ {
+            "Turret" => {
+                spawn_turret(
+                    commands,
+                    asset_server,
+                    serde_json::from_value(position)?,
+                    serde_json::from_value(rotation)?,
+                    bundle,
+                );
+            }
+            "Shotgun" => {
+                spawn_shotgun(
+                    commands,
+                    asset_server,
+                    serde_json::from_value(position)?,
+                    serde_json::from_value(rotation)?,
+                    bundle,
+                );
+            }
+            "Healer" => {
+                spawn_healer(
+                    commands,
+                    asset_server,
+                    serde_json::from_value(position)?,
+                    serde_json::from_value(rotation)?,
+                    bundle,
+                );
+            }
+            "MissileTower" => {
+                spawn_missile_tower(
+                    commands,
+                    asset_server,
+                    serde_json::from_value(position)?,
+                    serde_json::from_value(rotation)?,
+                    bundle,
+                );
+            }
+            "BeamTower" => {
+                spawn_beam_tower(
+                    commands,
+                    asset_server,
+                    serde_json::from_value(position)?,
+                    serde_json::from_value(rotation)?,
+                    bundle,
+                );
+            }
+            "CryoTower" => {
+                spawn_cryo_tower(
+                    commands,
+                    asset_server,
+                    serde_json::from_value(position)?,
+                    serde_json::from_value(rotation)?,
+                    bundle,
+                );
+            }
+            "MineLayer" => {
+                spawn_mine_layer(
+                    commands,
+                    asset_server,
+                    serde_json::from_value(position)?,
+                    serde_json::from_value(rotation)?,
+                    bundle,
+                );
+            }
+            "Amplifier" => {
+                spawn_amplifier(
+                    commands,
+                    asset_server,
+                    serde_json::from_value(position)?,
+                    serde_json::from_value(rotation)?,
+                    bundle,
+                );
+            }
+            _ => println!("Unrecognized type!"),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use crate::save::{load_settings, save_settings};
+use bevy::{prelude::*, window::WindowMode};
+use serde::{Deserialize, Serialize};
+
+pub(crate) struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_settings().unwrap_or_default());
+        app.add_system(settings_key_system);
+        app.add_system(fullscreen_system);
+        app.add_system(save_settings_system);
+    }
+}
+
+/// Player preferences that are not part of the game progress
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    /// Show floating damage numbers, exp and level ups
+    pub damage_numbers: bool,
+    pub health_bars: bool,
+    /// Volumes are in the range of 0 to 1, and the master volume scales the others
+    pub master_volume: f32,
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    /// Scale of the UI texts
+    pub ui_scale: f32,
+    pub fullscreen: bool,
+    /// Snap the towers being placed to the grid
+    pub snap_to_grid: bool,
+    pub key_bindings: KeyBindings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            damage_numbers: true,
+            health_bars: true,
+            master_volume: 1.,
+            sfx_volume: 0.7,
+            music_volume: 0.5,
+            ui_scale: 1.,
+            fullscreen: false,
+            snap_to_grid: false,
+            key_bindings: KeyBindings::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum KeyAction {
+    Pause,
+    DamageNumbers,
+    ClearSelection,
+    CycleTargeting,
+    Undo,
+    ToggleGrid,
+    HoldDirection,
+    ManualAim,
+    PlaybackSpeed,
+}
+
+impl KeyAction {
+    pub(crate) const ALL: [Self; 9] = [
+        Self::Pause,
+        Self::DamageNumbers,
+        Self::ClearSelection,
+        Self::CycleTargeting,
+        Self::Undo,
+        Self::ToggleGrid,
+        Self::HoldDirection,
+        Self::ManualAim,
+        Self::PlaybackSpeed,
+    ];
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            Self::Pause => "Pause",
+            Self::DamageNumbers => "Damage numbers",
+            Self::ClearSelection => "Clear selection",
+            Self::CycleTargeting => "Cycle targeting",
+            Self::Undo => "Undo (with Ctrl)",
+            Self::ToggleGrid => "Snap to grid",
+            Self::HoldDirection => "Hold direction",
+            Self::ManualAim => "Manual aim",
+            Self::PlaybackSpeed => "Replay speed",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct KeyBindings {
+    pause: KeyCode,
+    damage_numbers: KeyCode,
+    clear_selection: KeyCode,
+    cycle_targeting: KeyCode,
+    undo: KeyCode,
+    toggle_grid: KeyCode,
+    hold_direction: KeyCode,
+    manual_aim: KeyCode,
+    playback_speed: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            pause: KeyCode::P,
+            damage_numbers: KeyCode::N,
+            clear_selection: KeyCode::Escape,
+            cycle_targeting: KeyCode::T,
+            undo: KeyCode::Z,
+            toggle_grid: KeyCode::G,
+            hold_direction: KeyCode::H,
+            manual_aim: KeyCode::M,
+            playback_speed: KeyCode::F,
+        }
+    }
+}
+
+impl KeyBindings {
+    pub(crate) fn get(&self, action: KeyAction) -> KeyCode {
+        match action {
+            KeyAction::Pause => self.pause,
+            KeyAction::DamageNumbers => self.damage_numbers,
+            KeyAction::ClearSelection => self.clear_selection,
+            KeyAction::CycleTargeting => self.cycle_targeting,
+            KeyAction::Undo => self.undo,
+            KeyAction::ToggleGrid => self.toggle_grid,
+            KeyAction::HoldDirection => self.hold_direction,
+            KeyAction::ManualAim => self.manual_aim,
+            KeyAction::PlaybackSpeed => self.playback_speed,
+        }
+    }
+
+    pub(crate) fn set(&mut self, action: KeyAction, key: KeyCode) {
+        match action {
+            KeyAction::Pause => self.pause = key,
+            KeyAction::DamageNumbers => self.damage_numbers = key,
+            KeyAction::ClearSelection => self.clear_selection = key,
+            KeyAction::CycleTargeting => self.cycle_targeting = key,
+            KeyAction::Undo => self.undo = key,
+            KeyAction::ToggleGrid => self.toggle_grid = key,
+            KeyAction::HoldDirection => self.hold_direction = key,
+            KeyAction::ManualAim => self.manual_aim = key,
+            KeyAction::PlaybackSpeed => self.playback_speed = key,
+        }
+    }
+
+    pub(crate) fn just_pressed(&self, keys: &Input<KeyCode>, action: KeyAction) -> bool {
+        keys.just_pressed(self.get(action))
+    }
+}
+
+fn settings_key_system(keys: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    if settings
+        .key_bindings
+        .just_pressed(&keys, KeyAction::DamageNumbers)
+    {
+        settings.damage_numbers = !settings.damage_numbers;
+    }
+    if settings
+        .key_bindings
+        .just_pressed(&keys, KeyAction::ToggleGrid)
+    {
+        settings.snap_to_grid = !settings.snap_to_grid;
+    }
+}
+
+fn fullscreen_system(settings: Res<Settings>, mut windows: ResMut<Windows>) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        let mode = if settings.fullscreen {
+            WindowMode::BorderlessFullscreen
+        } else {
+            WindowMode::Windowed
+        };
+        if window.mode() != mode {
+            window.set_mode(mode);
+        }
+    }
+}
+
+fn save_settings_system(settings: Res<Settings>) {
+    // Don't write back what we have just loaded
+    if settings.is_changed() && !settings.is_added() {
+        save_settings(&settings);
+    }
+}
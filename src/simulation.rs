@@ -0,0 +1,179 @@
+//! The stage simulation runs in fixed steps with seeded random numbers, so that the same seed and
+//! the same player commands at the same steps play out a stage the same way, regardless of the
+//! frame rate.
+
+use crate::{
+    can_update,
+    mouse::{PendingPlacement, SelectedTower},
+    ui::PauseState,
+    Level,
+};
+use bevy::{ecs::schedule::ShouldRun, prelude::*, utils::Duration};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Seconds of a simulation step
+const STEP: f64 = 1. / 60.;
+/// Steps a frame can run at the normal speed. A slow frame drops the rest of its time, so that
+/// the next frames don't get even slower catching up.
+const MAX_STEPS_PER_FRAME: f64 = 4.;
+/// Steps a frame runs while seeking, without waiting for the time to pass
+const SEEK_STEPS_PER_FRAME: u32 = 300;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
+pub(crate) struct SimulationStage;
+
+pub(crate) struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SimTime);
+        app.insert_resource(SimClock::default());
+        app.insert_resource(GameRng(StdRng::from_entropy()));
+        // Single threaded, so that the systems run in the same order in every step
+        app.add_stage_after(
+            CoreStage::Update,
+            SimulationStage,
+            SystemStage::single_threaded().with_run_criteria(simulation_step),
+        );
+        app.add_system_to_stage(
+            SimulationStage,
+            count_step_system.exclusive_system().at_end(),
+        );
+    }
+}
+
+/// The time of a simulation step, for the simulation systems in place of `Time`
+pub(crate) struct SimTime;
+
+impl SimTime {
+    pub(crate) fn delta(&self) -> Duration {
+        Duration::from_secs_f64(STEP)
+    }
+
+    pub(crate) fn delta_seconds(&self) -> f32 {
+        STEP as f32
+    }
+}
+
+/// Random numbers of the simulation, seeded when a running stage starts
+#[derive(Deref, DerefMut)]
+pub(crate) struct GameRng(StdRng);
+
+/// Progress of the simulation steps
+pub(crate) struct SimClock {
+    /// Steps since the running stage started
+    pub step: u64,
+    /// The seed of the running stage
+    pub seed: u64,
+    /// The seed of the next stage instead of a random one
+    pub next_seed: Option<u64>,
+    /// Simulated seconds per real second
+    pub speed: f64,
+    /// The steps stop here until this is moved on, so that a command is applied exactly at its step
+    pub stop_at: Option<u64>,
+    /// Run the steps up to this one as fast as possible
+    pub seek: Option<u64>,
+    /// Seconds not simulated yet
+    accumulator: f64,
+    /// Steps left in the current frame, decided at the first check of the frame
+    steps_left: Option<u32>,
+    /// Whether the steps are counted for a running stage
+    running: bool,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        Self {
+            step: 0,
+            seed: 0,
+            next_seed: None,
+            speed: 1.,
+            stop_at: None,
+            seek: None,
+            accumulator: 0.,
+            steps_left: None,
+            running: false,
+        }
+    }
+}
+
+impl SimClock {
+    /// Whether the steps are counted, which begins with the first step of a running stage
+    pub(crate) fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Count the steps and seed the random numbers again from the next step, as if the running
+    /// stage just started.
+    pub(crate) fn restart(&mut self) {
+        self.running = false;
+    }
+
+    pub(crate) fn step_at(seconds: f32) -> u64 {
+        (seconds as f64 / STEP) as u64
+    }
+}
+
+/// Run the stage again for each step the frame time covers at the current speed.
+fn simulation_step(
+    time: Res<Time>,
+    level: Res<Level>,
+    selected_tower: Res<SelectedTower>,
+    pending: Res<PendingPlacement>,
+    pause_state: Res<PauseState>,
+    mut clock: ResMut<SimClock>,
+    mut rng: ResMut<GameRng>,
+) -> ShouldRun {
+    let running = level._is_running();
+    if !running {
+        clock.running = false;
+        clock.seek = None;
+    }
+
+    if clock.steps_left.is_none() {
+        clock.steps_left = Some(if clock.seek.is_some() {
+            SEEK_STEPS_PER_FRAME
+        } else {
+            clock.accumulator = (clock.accumulator + time.delta_seconds_f64() * clock.speed)
+                .min(MAX_STEPS_PER_FRAME * clock.speed * STEP);
+            let steps = (clock.accumulator / STEP) as u32;
+            clock.accumulator -= steps as f64 * STEP;
+            steps
+        });
+    }
+
+    if clock.running && matches!(clock.seek, Some(seek) if seek <= clock.step) {
+        clock.seek = None;
+        clock.steps_left = Some(0);
+    }
+
+    // Waiting for the player doesn't take steps, and a cleared stage ends at the step it cleared
+    let stopped = clock.running && matches!(clock.stop_at, Some(stop) if stop <= clock.step);
+    if stopped
+        || level.timer_finished()
+        || can_update(selected_tower, pending, pause_state) == ShouldRun::No
+    {
+        clock.accumulator = 0.;
+        clock.steps_left = Some(0);
+    }
+
+    if clock.steps_left == Some(0) {
+        clock.steps_left = None;
+        return ShouldRun::No;
+    }
+    clock.steps_left = clock.steps_left.map(|steps| steps - 1);
+
+    if running && !clock.running {
+        clock.running = true;
+        clock.step = 0;
+        clock.seed = clock.next_seed.take().unwrap_or_else(rand::random);
+        rng.0 = StdRng::seed_from_u64(clock.seed);
+    }
+    ShouldRun::YesAndCheckAgain
+}
+
+fn count_step_system(mut clock: ResMut<SimClock>) {
+    if clock.running {
+        clock.step += 1;
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{
+    settings::Settings,
+    tower::BeamTower,
+    ui::{not_paused, PauseState},
+    ClearEvent, Explosion, Level, Textures,
+};
+use bevy::{
+    asset::LoadState, audio::AudioSink, ecs::schedule::ShouldRun, prelude::*, utils::HashMap,
+};
+
+/// Plays of the same sound within this many seconds count as overlapping voices
+const VOICE_DURATION: f64 = 0.15;
+/// Overlapping voices allowed for one sound, so that a volley doesn't get deafening
+const MAX_VOICES_PER_SOUND: usize = 3;
+/// Overlapping voices allowed for all the sounds together
+const MAX_VOICES: usize = 12;
+
+pub(crate) struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SoundEvent>();
+        app.insert_resource(Voices::default());
+        app.add_startup_system(load_sounds);
+        app.add_system(explosion_sound_system);
+        app.add_system(clear_sound_system);
+        app.add_system(
+            play_sound_system
+                .after(explosion_sound_system)
+                .after(clear_sound_system),
+        );
+        app.add_system(beam_sound_system);
+        app.add_system(music_system);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Sound {
+    Shot,
+    ShotgunShot,
+    EnemyShot,
+    MissileLaunch,
+    SmallExplosion,
+    LargeExplosion,
+    LevelUp,
+    Place,
+    Trash,
+    StageClear,
+}
+
+impl Sound {
+    const ALL: [Self; 10] = [
+        Self::Shot,
+        Self::ShotgunShot,
+        Self::EnemyShot,
+        Self::MissileLaunch,
+        Self::SmallExplosion,
+        Self::LargeExplosion,
+        Self::LevelUp,
+        Self::Place,
+        Self::Trash,
+        Self::StageClear,
+    ];
+
+    fn file(&self) -> &'static str {
+        match self {
+            Self::Shot => "sounds/shot.ogg",
+            Self::ShotgunShot => "sounds/shotgun.ogg",
+            Self::EnemyShot => "sounds/enemy-shot.ogg",
+            Self::MissileLaunch => "sounds/missile.ogg",
+            Self::SmallExplosion => "sounds/explode.ogg",
+            Self::LargeExplosion => "sounds/explode2.ogg",
+            Self::LevelUp => "sounds/level-up.ogg",
+            Self::Place => "sounds/place.ogg",
+            Self::Trash => "sounds/trash.ogg",
+            Self::StageClear => "sounds/stage-clear.ogg",
+        }
+    }
+
+    /// Relative loudness, to keep the frequent sounds in the background
+    fn volume(&self) -> f32 {
+        match self {
+            Self::Shot | Self::EnemyShot | Self::SmallExplosion => 0.4,
+            Self::ShotgunShot | Self::MissileLaunch => 0.6,
+            _ => 1.,
+        }
+    }
+}
+
+pub(crate) struct SoundEvent(pub Sound);
+
+struct SoundAssets {
+    sounds: HashMap<Sound, Handle<AudioSource>>,
+    beam: Handle<AudioSource>,
+    /// A track for each difficulty
+    music: Vec<Handle<AudioSource>>,
+}
+
+/// Recent play times of each sound for voice limiting
+#[derive(Default)]
+struct Voices(HashMap<Sound, Vec<f64>>);
+
+fn load_sounds(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(SoundAssets {
+        sounds: Sound::ALL
+            .iter()
+            .map(|sound| (*sound, asset_server.load(sound.file())))
+            .collect(),
+        beam: asset_server.load("sounds/beam.ogg"),
+        music: (0..crate::MAX_DIFFICULTY)
+            .map(|difficulty| asset_server.load(&format!("music/stage{}.ogg", difficulty)))
+            .collect(),
+    });
+}
+
+/// Audio queues a sound until it is loaded, which would pile up forever if the file fails to
+/// load, so the sounds are only played once loaded.
+fn loaded(asset_server: &AssetServer, handle: &Handle<AudioSource>) -> bool {
+    asset_server.get_load_state(handle) == LoadState::Loaded
+}
+
+fn sfx_volume(settings: &Settings) -> f32 {
+    settings.master_volume * settings.sfx_volume
+}
+
+fn music_volume(settings: &Settings) -> f32 {
+    settings.master_volume * settings.music_volume
+}
+
+/// The explosion sprites already tell the size of the explosion, so we don't have to
+/// send events from every place that spawns one.
+fn explosion_sound_system(
+    textures: Res<Textures>,
+    query: Query<&Handle<TextureAtlas>, Added<Explosion>>,
+    mut writer: EventWriter<SoundEvent>,
+) {
+    for atlas in query.iter() {
+        writer.send(SoundEvent(if *atlas == textures.large_explosion {
+            Sound::LargeExplosion
+        } else {
+            Sound::SmallExplosion
+        }));
+    }
+}
+
+fn clear_sound_system(mut reader: EventReader<ClearEvent>, mut writer: EventWriter<SoundEvent>) {
+    if reader.iter().next().is_some() {
+        writer.send(SoundEvent(Sound::StageClear));
+    }
+}
+
+fn play_sound_system(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    audio: Res<Audio>,
+    asset_server: Res<AssetServer>,
+    sound_assets: Res<SoundAssets>,
+    mut voices: ResMut<Voices>,
+    mut reader: EventReader<SoundEvent>,
+) {
+    let now = time.seconds_since_startup();
+    for plays in voices.0.values_mut() {
+        plays.retain(|played| now - *played < VOICE_DURATION);
+    }
+    let mut total: usize = voices.0.values().map(|plays| plays.len()).sum();
+
+    let volume = sfx_volume(&settings);
+    for SoundEvent(sound) in reader.iter() {
+        if volume <= 0. || MAX_VOICES <= total {
+            continue;
+        }
+        let handle = if let Some(handle) = sound_assets.sounds.get(sound) {
+            handle
+        } else {
+            continue;
+        };
+        if !loaded(&asset_server, handle) {
+            continue;
+        }
+        let plays = voices.0.entry(*sound).or_default();
+        if MAX_VOICES_PER_SOUND <= plays.len() {
+            continue;
+        }
+        plays.push(now);
+        total += 1;
+        audio.play_with_settings(
+            handle.clone(),
+            PlaybackSettings::ONCE.with_volume(volume * sound.volume()),
+        );
+    }
+}
+
+/// Loop the beam sound while any beam tower is firing.
+fn beam_sound_system(
+    settings: Res<Settings>,
+    pause_state: Res<PauseState>,
+    audio: Res<Audio>,
+    asset_server: Res<AssetServer>,
+    sinks: Res<Assets<AudioSink>>,
+    sound_assets: Res<SoundAssets>,
+    query: Query<&BeamTower>,
+    mut beam_sink: Local<Option<Handle<AudioSink>>>,
+) {
+    let firing = not_paused(pause_state) == ShouldRun::Yes
+        && query.iter().any(|beamer| 0. < beamer.shoot_phase);
+
+    if let Some(sink) = beam_sink.as_ref() {
+        // The sink appears after the audio output picks up the queued sound
+        if let Some(sink) = sinks.get(sink) {
+            sink.set_volume(sfx_volume(&settings));
+            if firing {
+                sink.play();
+            } else {
+                sink.pause();
+            }
+        }
+    } else if firing && loaded(&asset_server, &sound_assets.beam) {
+        let sink = audio.play_with_settings(
+            sound_assets.beam.clone(),
+            PlaybackSettings::LOOP.with_volume(sfx_volume(&settings)),
+        );
+        *beam_sink = Some(sinks.get_handle(sink));
+    }
+}
+
+/// Switch the music track with the difficulty of the running stage.
+fn music_system(
+    settings: Res<Settings>,
+    level: Res<Level>,
+    audio: Res<Audio>,
+    asset_server: Res<AssetServer>,
+    sinks: Res<Assets<AudioSink>>,
+    sound_assets: Res<SoundAssets>,
+    mut playing: Local<Option<(usize, Handle<AudioSink>)>>,
+) {
+    let difficulty = match level.as_ref() {
+        Level::Build { difficulty, .. } | Level::Running { difficulty, .. } => Some(*difficulty),
+        Level::Select => None,
+    };
+
+    if playing.as_ref().map(|(playing, _)| *playing) != difficulty {
+        if let Some(sink) = playing.take().and_then(|(_, sink)| sinks.get(sink)) {
+            sink.stop();
+        }
+    }
+
+    if let Some((_, sink)) = playing.as_ref() {
+        if settings.is_changed() {
+            if let Some(sink) = sinks.get(sink) {
+                sink.set_volume(music_volume(&settings));
+            }
+        }
+        return;
+    }
+
+    let track = if let Some(track) = difficulty.and_then(|d| sound_assets.music.get(d)) {
+        track
+    } else {
+        return;
+    };
+    if loaded(&asset_server, track) {
+        let sink = audio.play_with_settings(
+            track.clone(),
+            PlaybackSettings::LOOP.with_volume(music_volume(&settings)),
+        );
+        *playing = difficulty.zip(Some(sinks.get_handle(sink)));
+    }
+}
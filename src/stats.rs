@@ -0,0 +1,183 @@
+use crate::{
+    simulation::{SimTime, SimulationStage},
+    Health,
+};
+use bevy::prelude::*;
+
+pub(crate) struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(SimulationStage, modifier_timeout_system);
+        app.add_system_to_stage(SimulationStage, sync_health_system);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Stat {
+    /// Damage per bullet, mine or beam second
+    Damage,
+    /// Multiplier to the speed the weapon cooldown elapses
+    FireRate,
+    HealAmount,
+    MaxHealth,
+}
+
+const STAT_COUNT: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ModifierSource {
+    /// Scaling by the tower level
+    Level,
+    /// Support tower auras
+    Aura,
+    /// Temporary buffs that are given a duration
+    #[allow(dead_code)]
+    Buff,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ModifierOp {
+    Add(f32),
+    Mul(f32),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Modifier {
+    pub stat: Stat,
+    pub source: ModifierSource,
+    pub op: ModifierOp,
+    /// Remaining seconds, or None if permanent
+    pub duration: Option<f32>,
+}
+
+/// Base values and a stack of modifiers.
+///
+/// The derived value of a stat is `(base + sum of Add) * product of Mul`. There is at most one
+/// modifier for each pair of stat and source, so setting a modifier replaces the previous one
+/// from the same source instead of stacking on it.
+#[derive(Component, Clone, Debug)]
+pub(crate) struct Stats {
+    base: [f32; STAT_COUNT],
+    modifiers: Vec<Modifier>,
+    derived: [f32; STAT_COUNT],
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        let mut base = [0.; STAT_COUNT];
+        base[Stat::FireRate as usize] = 1.;
+        Self {
+            base,
+            modifiers: vec![],
+            derived: base,
+        }
+    }
+}
+
+impl Stats {
+    pub(crate) fn with_base(mut self, stat: Stat, value: f32) -> Self {
+        self.set_base(stat, value);
+        self
+    }
+
+    pub(crate) fn set_base(&mut self, stat: Stat, value: f32) {
+        self.base[stat as usize] = value;
+        self.recompute();
+    }
+
+    pub(crate) fn get(&self, stat: Stat) -> f32 {
+        self.derived[stat as usize]
+    }
+
+    /// Derived value as if the modifiers from `source` didn't exist
+    pub(crate) fn get_without(&self, stat: Stat, source: ModifierSource) -> f32 {
+        self.evaluate(stat, |modifier| modifier.source != source)
+    }
+
+    pub(crate) fn modifier(&self, stat: Stat, source: ModifierSource) -> Option<ModifierOp> {
+        self.modifiers
+            .iter()
+            .find(|modifier| modifier.stat == stat && modifier.source == source)
+            .map(|modifier| modifier.op)
+    }
+
+    pub(crate) fn set_modifier(&mut self, modifier: Modifier) {
+        if let Some(existing) = self
+            .modifiers
+            .iter_mut()
+            .find(|existing| existing.stat == modifier.stat && existing.source == modifier.source)
+        {
+            *existing = modifier;
+        } else {
+            self.modifiers.push(modifier);
+        }
+        self.recompute();
+    }
+
+    pub(crate) fn remove_modifier(&mut self, stat: Stat, source: ModifierSource) {
+        self.modifiers
+            .retain(|modifier| modifier.stat != stat || modifier.source != source);
+        self.recompute();
+    }
+
+    fn evaluate(&self, stat: Stat, filter: impl Fn(&Modifier) -> bool) -> f32 {
+        let (add, mul) = self
+            .modifiers
+            .iter()
+            .filter(|modifier| modifier.stat == stat && filter(modifier))
+            .fold((0., 1.), |(add, mul), modifier| match modifier.op {
+                ModifierOp::Add(value) => (add + value, mul),
+                ModifierOp::Mul(value) => (add, mul * value),
+            });
+        (self.base[stat as usize] + add) * mul
+    }
+
+    fn recompute(&mut self) {
+        for stat in [
+            Stat::Damage,
+            Stat::FireRate,
+            Stat::HealAmount,
+            Stat::MaxHealth,
+        ] {
+            self.derived[stat as usize] = self.evaluate(stat, |_| true);
+        }
+    }
+}
+
+fn modifier_timeout_system(time: Res<SimTime>, mut query: Query<&mut Stats>) {
+    let delta = time.delta_seconds();
+    for mut stats in query.iter_mut() {
+        // Check before borrowing mutably to avoid triggering change detection every frame
+        if !stats
+            .modifiers
+            .iter()
+            .any(|modifier| modifier.duration.is_some())
+        {
+            continue;
+        }
+        for modifier in stats.modifiers.iter_mut() {
+            if let Some(ref mut duration) = modifier.duration {
+                *duration -= delta;
+            }
+        }
+        let count = stats.modifiers.len();
+        stats
+            .modifiers
+            .retain(|modifier| modifier.duration.map(|d| 0. < d).unwrap_or(true));
+        if stats.modifiers.len() != count {
+            stats.recompute();
+        }
+    }
+}
+
+/// `Health` is read by many systems, so keep its maximum in sync with the derived stat.
+fn sync_health_system(mut query: Query<(&Stats, &mut Health), Changed<Stats>>) {
+    for (stats, mut health) in query.iter_mut() {
+        let max = stats.get(Stat::MaxHealth).ceil();
+        if health.max != max {
+            health.max = max;
+            health.val = health.val.min(max);
+        }
+    }
+}
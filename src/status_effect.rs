@@ -0,0 +1,186 @@
+use crate::{
+    bullet::GainExpEvent,
+    damage::{apply_damage, DamageType, Shield},
+    simulation::{SimTime, SimulationStage},
+    BulletFilter, Health,
+};
+use bevy::prelude::*;
+
+pub(crate) struct StatusEffectPlugin;
+
+impl Plugin for StatusEffectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(SimulationStage, status_effect_system);
+        app.add_system(status_tint_system);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum StatusEffectKind {
+    /// Scales movement and turn speed by `1 - magnitude`
+    Slow,
+    /// Deals `magnitude` damage per second
+    Burn,
+    /// Disables shooting
+    Stun,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct StatusEffect {
+    pub kind: StatusEffectKind,
+    pub magnitude: f32,
+    /// Remaining seconds
+    pub duration: f32,
+    /// The entity that receives experience if this effect kills the target
+    pub source: Option<Entity>,
+}
+
+/// Slow cannot reduce the speed below this fraction
+const MAX_SLOW: f32 = 0.8;
+/// Burns from different hits stack up to this number
+const MAX_BURN_STACKS: usize = 5;
+
+/// Timed effects on an entity.
+///
+/// Stacking rules:
+/// * Slow and Stun don't stack. The strongest slow is kept and the durations are refreshed
+///   to the longer one.
+/// * Burn stacks up to `MAX_BURN_STACKS` instances, replacing the one that expires first.
+#[derive(Component, Default)]
+pub(crate) struct StatusEffects {
+    effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    pub(crate) fn apply(&mut self, effect: StatusEffect) {
+        match effect.kind {
+            StatusEffectKind::Slow | StatusEffectKind::Stun => {
+                if let Some(existing) = self.effects.iter_mut().find(|e| e.kind == effect.kind) {
+                    existing.magnitude = existing.magnitude.max(effect.magnitude);
+                    existing.duration = existing.duration.max(effect.duration);
+                    existing.source = effect.source.or(existing.source);
+                } else {
+                    self.effects.push(effect);
+                }
+            }
+            StatusEffectKind::Burn => {
+                let burns = self
+                    .effects
+                    .iter()
+                    .filter(|e| e.kind == StatusEffectKind::Burn)
+                    .count();
+                if burns < MAX_BURN_STACKS {
+                    self.effects.push(effect);
+                } else if let Some(shortest) = self
+                    .effects
+                    .iter_mut()
+                    .filter(|e| e.kind == StatusEffectKind::Burn)
+                    .min_by(|a, b| a.duration.partial_cmp(&b.duration).unwrap())
+                {
+                    *shortest = effect;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn has(&self, kind: StatusEffectKind) -> bool {
+        self.effects.iter().any(|e| e.kind == kind)
+    }
+
+    /// Multiplier for movement and turn speed
+    pub(crate) fn speed_factor(&self) -> f32 {
+        1. - self
+            .effects
+            .iter()
+            .filter(|e| e.kind == StatusEffectKind::Slow)
+            .fold(0., |acc: f32, e| acc.max(e.magnitude))
+            .min(MAX_SLOW)
+    }
+
+    pub(crate) fn is_stunned(&self) -> bool {
+        self.has(StatusEffectKind::Stun)
+    }
+}
+
+/// A helper to get the speed factor from an optional component.
+pub(crate) fn speed_factor(status_effects: Option<&StatusEffects>) -> f32 {
+    status_effects
+        .map(|status_effects| status_effects.speed_factor())
+        .unwrap_or(1.)
+}
+
+fn status_effect_system(
+    time: Res<SimTime>,
+    mut query: Query<(
+        &mut StatusEffects,
+        &mut Health,
+        &BulletFilter,
+        Option<&mut Shield>,
+    )>,
+    mut exp_event: EventWriter<GainExpEvent>,
+) {
+    let delta = time.delta_seconds();
+    for (mut status_effects, mut health, bullet_filter, mut shield) in query.iter_mut() {
+        if status_effects.effects.is_empty() {
+            continue;
+        }
+
+        for effect in status_effects.effects.iter() {
+            if effect.kind != StatusEffectKind::Burn || health.val <= 0. {
+                continue;
+            }
+            // Burn ignores armor and resistances, but the shield still protects
+            apply_damage(
+                &mut health,
+                (None, None, shield.as_deref_mut()),
+                effect.magnitude * delta,
+                DamageType::Energy,
+                delta,
+            );
+            if health.val <= 0. {
+                health.val = 0.;
+                if let Some(source) = effect.source {
+                    exp_event.send(GainExpEvent {
+                        entity: source,
+                        exp: bullet_filter.exp,
+                        killed: true,
+                    });
+                }
+            }
+        }
+
+        for effect in status_effects.effects.iter_mut() {
+            effect.duration -= delta;
+        }
+        status_effects.effects.retain(|e| 0. < e.duration);
+    }
+}
+
+/// Color of a sprite while no effect is active
+#[derive(Component)]
+pub(crate) struct BaseTint(pub Color);
+
+/// Tint the sprite children according to the active effects.
+fn status_tint_system(
+    query: Query<(&StatusEffects, &Children), Changed<StatusEffects>>,
+    mut query_sprite: Query<(&mut Sprite, Option<&BaseTint>)>,
+) {
+    for (status_effects, children) in query.iter() {
+        let color = if status_effects.is_stunned() {
+            Some(Color::rgb(1., 1., 0.4))
+        } else if status_effects.has(StatusEffectKind::Burn) {
+            Some(Color::rgb(1., 0.5, 0.3))
+        } else if status_effects.has(StatusEffectKind::Slow) {
+            Some(Color::rgb(0.5, 0.7, 1.))
+        } else {
+            None
+        };
+        for child in children.iter() {
+            if let Ok((mut sprite, base_tint)) = query_sprite.get_mut(*child) {
+                sprite.color = color
+                    .or_else(|| base_tint.map(|base_tint| base_tint.0))
+                    .unwrap_or(Color::WHITE);
+            }
+        }
+    }
+}
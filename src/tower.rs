@@ -1,25 +1,41 @@
+mod amplifier;
 mod beam_tower;
+mod cryo_tower;
 mod healer;
+mod mine_layer;
 
 use self::{
+    amplifier::amplifier_system,
     beam_tower::{beam_tower_find_target, shoot_beam},
+    cryo_tower::{cryo_aura_system, cryo_pulse_system},
     healer::{heal_target, healer_find_target},
+    mine_layer::{mine_layer_system, mine_trigger_system},
 };
 use crate::{
+    arena::ArenaBounds,
     bullet::{BulletShooter, GainExpEvent},
-    can_update, BulletFilter, Enemy, Health, Position, Rotation, Target,
+    damage::DamageType,
+    floating_text::FloatingTextEvent,
+    settings::Settings,
+    simulation::{SimTime, SimulationStage},
+    sound::{Sound, SoundEvent},
+    stats::{Modifier, ModifierOp, ModifierSource, Stat, Stats},
+    BulletFilter, Enemy, Health, Position, Rotation, Target,
 };
 use ::serde::{Deserialize, Serialize};
 use bevy::prelude::*;
 use bevy_prototype_lyon::{entity::ShapeBundle, prelude::*, shapes::Circle};
 
 pub(crate) use self::{
-    beam_tower::{spawn_beam_tower, BeamTower},
-    healer::{spawn_healer, Healer},
+    amplifier::{spawn_amplifier, Amplifier, AMPLIFIER_COLOR},
+    beam_tower::{spawn_beam_tower, BeamTower, BEAM_RANGE, BEAM_TOWER_SIZE},
+    cryo_tower::{spawn_cryo_tower, CryoTower, CRYO_TOWER_COLOR},
+    healer::{spawn_healer, Healer, HEALER_RANGE},
+    mine_layer::{spawn_mine_layer, MineLayer, MINE_DROP_RANGE, MINE_LAYER_COLOR},
 };
 
-const TOWER_SIZE: f32 = 32.;
-const MISSILE_TOWER_SIZE: f32 = 48.;
+pub(crate) const TOWER_SIZE: f32 = 32.;
+pub(crate) const MISSILE_TOWER_SIZE: f32 = 48.;
 
 #[derive(Component, Serialize, Deserialize)]
 pub(crate) struct Tower {
@@ -40,6 +56,64 @@ pub(crate) struct TowerScore {
     pub kills: usize,
 }
 
+/// Which enemy a tower picks as the target
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Targeting {
+    #[default]
+    Nearest,
+    Weakest,
+    Strongest,
+}
+
+impl Targeting {
+    pub(crate) fn next(&self) -> Self {
+        match self {
+            Self::Nearest => Self::Weakest,
+            Self::Weakest => Self::Strongest,
+            Self::Strongest => Self::Nearest,
+        }
+    }
+
+    /// The enemy with the lowest score is the target
+    pub(crate) fn score(&self, distance: f32, health: Option<&Health>) -> f32 {
+        let health = health.map(|health| health.val).unwrap_or(0.);
+        match self {
+            Self::Nearest => distance,
+            Self::Weakest => health,
+            Self::Strongest => -health,
+        }
+    }
+}
+
+/// Firing arc of a tower that starts holding a direction, in radians
+pub(crate) const DEFAULT_HOLD_ARC: f64 = std::f64::consts::FRAC_PI_2;
+pub(crate) const MIN_HOLD_ARC: f64 = std::f64::consts::PI / 12.;
+pub(crate) const MAX_HOLD_ARC: f64 = std::f64::consts::PI * 2.;
+
+/// How a tower turns to aim
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) enum AimMode {
+    /// Turn toward the target
+    #[default]
+    Auto,
+    /// Only target the enemies within the arc around the direction, and face the direction
+    /// when there are none
+    Hold { direction: f64, arc: f64 },
+    /// Face the mouse cursor and fire while the button is held
+    Manual,
+}
+
+impl AimMode {
+    /// Whether the tower may pick a target in the direction
+    pub(crate) fn allows(&self, angle: f64) -> bool {
+        match self {
+            Self::Auto => true,
+            Self::Hold { direction, arc } => wrap_angle(angle - direction).abs() <= arc / 2.,
+            Self::Manual => false,
+        }
+    }
+}
+
 #[derive(Component, Serialize, Deserialize)]
 pub(crate) struct Shotgun;
 
@@ -47,7 +121,7 @@ pub(crate) struct Shotgun;
 pub(crate) struct MissileShooter;
 
 #[derive(Component)]
-pub(crate) struct Timeout(f32);
+pub(crate) struct Timeout(pub f32);
 
 /// Indicates temporary entities
 #[derive(Component)]
@@ -62,7 +136,10 @@ pub(crate) struct TowerBundle {
     tower_score: TowerScore,
     health: Health,
     target: Target,
+    targeting: Targeting,
+    aim_mode: AimMode,
     bullet_filter: BulletFilter,
+    stats: Stats,
 }
 
 impl TowerBundle {
@@ -73,6 +150,21 @@ impl TowerBundle {
         size: f32,
         bundle: TowerInitBundle,
     ) -> Self {
+        let health = bundle.health.unwrap();
+        let tower_level = bundle.tower_level.unwrap_or(TowerLevel {
+            level: 0,
+            exp: 0,
+            max_health_base: 10.,
+            max_health_exponent: 1.2,
+        });
+        // The health of each tower type only applies until the first level up
+        let max_health_base = if tower_level.level == 0 {
+            health.max
+        } else {
+            tower_level.max_health_base
+        };
+        let mut stats = Stats::default().with_base(Stat::MaxHealth, max_health_base);
+        apply_level_modifiers(&mut stats, &tower_level);
         Self {
             position,
             rotation,
@@ -80,22 +172,44 @@ impl TowerBundle {
                 health_bar: health_bar(commands),
                 size,
             },
-            tower_level: bundle.tower_level.unwrap_or(TowerLevel {
-                level: 0,
-                exp: 0,
-                max_health_base: 10.,
-                max_health_exponent: 1.2,
-            }),
+            tower_level,
             tower_score: bundle.tower_score.unwrap_or(TowerScore { kills: 0 }),
-            health: bundle.health.unwrap(),
+            health,
             target: Target(None),
+            targeting: bundle.targeting.unwrap_or_default(),
+            aim_mode: bundle.aim_mode.unwrap_or_default(),
             bullet_filter: BulletFilter {
                 filter: false,
                 radius: 10.,
                 exp: 10,
             },
+            stats,
         }
     }
+
+    pub(crate) fn with_base_stat(mut self, stat: Stat, value: f32) -> Self {
+        self.stats.set_base(stat, value);
+        self
+    }
+}
+
+/// Set the modifiers that scale the stats with the tower level
+fn apply_level_modifiers(stats: &mut Stats, tower_level: &TowerLevel) {
+    let level = tower_level.level as f32;
+    let mut set = |stat, op| {
+        stats.set_modifier(Modifier {
+            stat,
+            source: ModifierSource::Level,
+            op,
+            duration: None,
+        })
+    };
+    set(Stat::Damage, ModifierOp::Mul((1.2f32).powf(level)));
+    set(Stat::HealAmount, ModifierOp::Add(0.1 * level));
+    set(
+        Stat::MaxHealth,
+        ModifierOp::Mul(tower_level.max_health_exponent.powf(level)),
+    );
 }
 
 fn shape_from_size(size: f32) -> ShapeBundle {
@@ -118,17 +232,23 @@ pub(crate) struct TowerPlugin;
 
 impl Plugin for TowerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(update_health_bar).add_system_set(
+        app.add_system(update_health_bar).add_system_set_to_stage(
+            SimulationStage,
             SystemSet::new()
-                .with_run_criteria(can_update)
                 .with_system(tower_find_target)
+                .with_system(manual_aim_system)
                 .with_system(healer_find_target)
                 .with_system(heal_target)
                 .with_system(beam_tower_find_target)
                 .with_system(shoot_beam)
+                .with_system(cryo_aura_system)
+                .with_system(mine_layer_system)
+                .with_system(mine_trigger_system)
+                .with_system(amplifier_system)
                 .with_system(timeout),
         );
-        app.add_system(tower_killed_system);
+        app.add_system_to_stage(SimulationStage, tower_killed_system);
+        app.add_system(cryo_pulse_system);
     }
 }
 
@@ -137,6 +257,9 @@ const SHOTGUN_HEALTH: Health = Health::new(20.);
 const HEALER_HEALTH: Health = Health::new(20.);
 const MISSILE_HEALTH: Health = Health::new(30.);
 const BEAM_TOWER_HEALTH: Health = Health::new(30.);
+const CRYO_TOWER_HEALTH: Health = Health::new(20.);
+const MINE_LAYER_HEALTH: Health = Health::new(20.);
+const AMPLIFIER_HEALTH: Health = Health::new(20.);
 
 pub(crate) fn spawn_towers(commands: &mut Commands, asset_server: &Res<AssetServer>) {
     for i in 0..2 {
@@ -155,13 +278,28 @@ pub(crate) struct TowerInitBundle {
     pub tower_level: Option<TowerLevel>,
     pub tower_score: Option<TowerScore>,
     pub health: Option<Health>,
+    pub targeting: Option<Targeting>,
+    pub aim_mode: Option<AimMode>,
 }
 
-fn bullet_shooter_from_level(tower_level: &Option<TowerLevel>, missile: bool) -> BulletShooter {
-    BulletShooter::new(
+fn bullet_shooter_from_level(
+    tower_level: &Option<TowerLevel>,
+    missile: bool,
+    shotgun: bool,
+) -> BulletShooter {
+    let level = tower_level.as_ref().map(|l| l.level).unwrap_or(0);
+    let mut bullet_shooter = BulletShooter::new(
         false,
-        bullet_damage_by_level(tower_level.as_ref().map(|l| l.level).unwrap_or(0), missile),
-    )
+        bullet_base_damage(missile),
+        if missile {
+            DamageType::Explosive
+        } else {
+            DamageType::Kinetic
+        },
+    );
+    (bullet_shooter.pierce, bullet_shooter.ricochet) =
+        bullet_pierce_ricochet_by_level(level, missile, shotgun);
+    bullet_shooter
 }
 
 fn tower_sprite_bundle(texture_name: &str, asset_server: &AssetServer, scale: f32) -> SpriteBundle {
@@ -187,7 +325,7 @@ pub(crate) fn spawn_turret(
     rotation: f64,
     bundle: TowerInitBundle,
 ) -> Entity {
-    let bullet_shooter = bullet_shooter_from_level(&bundle.tower_level, false);
+    let bullet_shooter = bullet_shooter_from_level(&bundle.tower_level, false, false);
     let tower = TowerBundle::new(
         commands,
         Position(position),
@@ -197,7 +335,8 @@ pub(crate) fn spawn_turret(
             health: Some(bundle.health.unwrap_or(TOWER_HEALTH)),
             ..bundle
         },
-    );
+    )
+    .with_base_stat(Stat::Damage, bullet_shooter.damage);
     let sprite = commands
         .spawn_bundle(tower_sprite_bundle("turret.png", asset_server, 3.))
         .id();
@@ -218,7 +357,7 @@ pub(crate) fn spawn_shotgun(
     rotation: f64,
     bundle: TowerInitBundle,
 ) -> Entity {
-    let bullet_shooter = bullet_shooter_from_level(&bundle.tower_level, false);
+    let bullet_shooter = bullet_shooter_from_level(&bundle.tower_level, false, true);
     let tower = TowerBundle::new(
         commands,
         Position(position),
@@ -228,7 +367,8 @@ pub(crate) fn spawn_shotgun(
             health: Some(bundle.health.unwrap_or(SHOTGUN_HEALTH)),
             ..bundle
         },
-    );
+    )
+    .with_base_stat(Stat::Damage, bullet_shooter.damage);
     let sprite = commands
         .spawn_bundle(tower_sprite_bundle("shotgun.png", asset_server, 3.))
         .id();
@@ -250,7 +390,7 @@ pub(crate) fn spawn_missile_tower(
     rotation: f64,
     bundle: TowerInitBundle,
 ) -> Entity {
-    let bullet_shooter = bullet_shooter_from_level(&bundle.tower_level, true);
+    let bullet_shooter = bullet_shooter_from_level(&bundle.tower_level, true, false);
     let tower = TowerBundle::new(
         commands,
         Position(position),
@@ -260,7 +400,8 @@ pub(crate) fn spawn_missile_tower(
             health: Some(bundle.health.unwrap_or(MISSILE_HEALTH)),
             ..bundle
         },
-    );
+    )
+    .with_base_stat(Stat::Damage, bullet_shooter.damage);
     let sprite = commands
         .spawn_bundle(tower_sprite_bundle("missile-tower.png", asset_server, 3.))
         .id();
@@ -307,11 +448,19 @@ fn health_bar(commands: &mut Commands) -> (Entity, Entity) {
 }
 
 pub(crate) fn update_health_bar(
+    settings: Res<Settings>,
     query: Query<(&Position, &Tower, &Health)>,
-    mut query_health_bar: Query<&mut Transform>,
+    mut query_health_bar: Query<(&mut Transform, &mut Visibility)>,
 ) {
     for (position, tower, health) in query.iter() {
-        if let Ok(mut bar) = query_health_bar.get_mut(tower.health_bar.0) {
+        for bar in [tower.health_bar.0, tower.health_bar.1] {
+            if let Ok((_, mut visibility)) = query_health_bar.get_mut(bar) {
+                if visibility.is_visible != settings.health_bars {
+                    visibility.is_visible = settings.health_bars;
+                }
+            }
+        }
+        if let Ok((mut bar, _)) = query_health_bar.get_mut(tower.health_bar.0) {
             let factor = health.val / health.max;
             *bar = Transform::from_xyz(
                 position.0.x - (1. - factor) * HEALTH_BAR_WIDTH / 2.,
@@ -320,12 +469,20 @@ pub(crate) fn update_health_bar(
             )
             .with_scale(Vec3::new(factor, 1., 1.));
         }
-        if let Ok(mut bar) = query_health_bar.get_mut(tower.health_bar.1) {
+        if let Ok((mut bar, _)) = query_health_bar.get_mut(tower.health_bar.1) {
             *bar = Transform::from_xyz(position.0.x, position.0.y + 50., 0.5);
         }
     }
 }
 
+/// Wrap an angle into the range of -PI to PI
+pub(crate) fn wrap_angle(angle: f64) -> f64 {
+    use std::f64::consts::PI;
+    const TWOPI: f64 = PI * 2.;
+
+    ((angle + PI) - ((angle + PI) / TWOPI).floor() * TWOPI) - PI
+}
+
 /// Try to approach the target angle from current angle.
 /// Returns a pair (angle, in_range) where angle is the result angle and in_range is whether
 /// the target is close to the target (thus allowed to shoot).
@@ -337,8 +494,7 @@ pub(super) fn apprach_angle(
     use std::f64::consts::PI;
     const TWOPI: f64 = PI * 2.;
 
-    let delta_angle = target_angle - current_angle;
-    let wrap_angle = ((delta_angle + PI) - ((delta_angle + PI) / TWOPI).floor() * TWOPI) - PI;
+    let wrap_angle = wrap_angle(target_angle - current_angle);
 
     if wrap_angle.abs() < angle_speed {
         (target_angle, true)
@@ -356,24 +512,41 @@ pub(super) fn apprach_angle(
 }
 
 fn tower_find_target(
-    mut query: Query<(&mut Rotation, &Position, &mut BulletShooter, &mut Target), With<Tower>>,
-    enemy_query: Query<(Entity, &Position), With<Enemy>>,
-    time: Res<Time>,
+    mut query: Query<
+        (
+            &mut Rotation,
+            &Position,
+            &mut BulletShooter,
+            &mut Target,
+            &Targeting,
+            &AimMode,
+        ),
+        With<Tower>,
+    >,
+    enemy_query: Query<(Entity, &Position, Option<&Health>), With<Enemy>>,
+    time: Res<SimTime>,
 ) {
     let delta_time = time.delta_seconds();
-    for (mut rotation, position, mut bullet_shooter, mut target) in query.iter_mut() {
+    for (mut rotation, position, mut bullet_shooter, mut target, targeting, aim_mode) in
+        query.iter_mut()
+    {
+        // Aimed by manual_aim_system
+        if *aim_mode == AimMode::Manual {
+            continue;
+        }
         let new_target = enemy_query
             .iter()
-            .fold(None, |acc, (enemy_entity, enemy_position)| {
-                let this_dist = enemy_position.0.distance(position.0);
-                if let Some((prev_dist, _, _)) = acc {
-                    if this_dist < prev_dist {
-                        Some((this_dist, enemy_entity, enemy_position))
+            .filter(|(_, enemy_position, _)| aim_mode.allows(angle_to(position, enemy_position)))
+            .fold(None, |acc, (enemy_entity, enemy_position, health)| {
+                let this_score = targeting.score(enemy_position.0.distance(position.0), health);
+                if let Some((prev_score, _, _)) = acc {
+                    if this_score < prev_score {
+                        Some((this_score, enemy_entity, enemy_position))
                     } else {
                         acc
                     }
                 } else {
-                    Some((this_dist, enemy_entity, enemy_position))
+                    Some((this_score, enemy_entity, enemy_position))
                 }
             });
 
@@ -389,14 +562,73 @@ fn tower_find_target(
             (rotation.0, bullet_shooter.enabled) =
                 apprach_angle(rotation.0, target_angle, ANGLE_SPEED * delta_time as f64);
         } else {
+            target.0 = None;
             bullet_shooter.enabled = false;
+            if let AimMode::Hold { direction, .. } = aim_mode {
+                (rotation.0, _) =
+                    apprach_angle(rotation.0, *direction, ANGLE_SPEED * delta_time as f64);
+            }
+        }
+    }
+}
+
+pub(crate) fn angle_to(from: &Position, to: &Position) -> f64 {
+    let delta = to.0 - from.0;
+    delta.y.atan2(delta.x) as f64
+}
+
+/// Turn the towers in manual aim toward the cursor. Bullet towers fire while the mouse button
+/// is held, and beam towers start a beam on click.
+fn manual_aim_system(
+    windows: Res<Windows>,
+    arena: Res<ArenaBounds>,
+    btn: Res<Input<MouseButton>>,
+    time: Res<SimTime>,
+    mut query: Query<
+        (
+            &mut Rotation,
+            &Position,
+            &AimMode,
+            Option<&mut BulletShooter>,
+            Option<&mut BeamTower>,
+        ),
+        With<Tower>,
+    >,
+) {
+    use std::f64::consts::PI;
+    const ANGLE_SPEED: f64 = PI * 2.;
+
+    let mouse_screen = windows
+        .get_primary()
+        .and_then(|window| Some(arena.window_to_world(window, window.cursor_position()?)));
+    let delta_time = time.delta_seconds() as f64;
+
+    for (mut rotation, position, aim_mode, bullet_shooter, beam_tower) in query.iter_mut() {
+        if *aim_mode != AimMode::Manual {
+            continue;
+        }
+        let aimed = if let Some(mouse_screen) = mouse_screen {
+            let target_angle = angle_to(position, &Position(mouse_screen));
+            let aimed;
+            (rotation.0, aimed) = apprach_angle(rotation.0, target_angle, ANGLE_SPEED * delta_time);
+            aimed
+        } else {
+            false
+        };
+        if let Some(mut bullet_shooter) = bullet_shooter {
+            bullet_shooter.enabled = aimed && btn.pressed(MouseButton::Left);
+        }
+        if let Some(mut beam_tower) = beam_tower {
+            if aimed && btn.just_pressed(MouseButton::Left) {
+                beam_tower.fire();
+            }
         }
     }
 }
 
 fn timeout(
     mut commands: Commands,
-    time: Res<Time>,
+    time: Res<SimTime>,
     mut query: Query<(Entity, &mut Sprite, &mut Timeout)>,
 ) {
     let delta = time.delta_seconds();
@@ -421,20 +653,26 @@ fn tower_killed_system(
         &mut TowerLevel,
         &mut Health,
         &mut TowerScore,
+        &mut Stats,
+        &Position,
         Option<&mut BulletShooter>,
-        Option<&mut Healer>,
         Option<&MissileShooter>,
+        Option<&Shotgun>,
     )>,
     mut reader: EventReader<GainExpEvent>,
+    mut text_writer: EventWriter<FloatingTextEvent>,
+    mut sound_writer: EventWriter<SoundEvent>,
 ) {
     for event in reader.iter() {
         if let Ok((
             mut tower,
             mut health,
             mut scoring_tower,
+            mut stats,
+            position,
             mut bullet_shooter,
-            mut healer,
             missile_tower,
+            shotgun,
         )) = query.get_mut(event.entity)
         {
             if event.killed {
@@ -442,29 +680,49 @@ fn tower_killed_system(
             }
 
             tower.exp += event.exp;
+            let mut leveled_up = false;
             while tower_max_exp(tower.level) <= tower.exp {
                 tower.level += 1;
-                health.max = (tower.max_health_exponent.powf(tower.level as f32)
-                    * tower.max_health_base)
-                    .ceil();
-                health.val = health.max;
+                leveled_up = true;
                 if let Some(ref mut bullet_shooter) = bullet_shooter {
-                    bullet_shooter.damage =
-                        bullet_damage_by_level(tower.level, missile_tower.is_some());
-                }
-                if let Some(ref mut healer) = healer {
-                    healer.heal_amt = heal_amt_by_level(tower.level);
+                    (bullet_shooter.pierce, bullet_shooter.ricochet) =
+                        bullet_pierce_ricochet_by_level(
+                            tower.level,
+                            missile_tower.is_some(),
+                            shotgun.is_some(),
+                        );
                 }
             }
+
+            if leveled_up {
+                stats.set_base(Stat::MaxHealth, tower.max_health_base);
+                apply_level_modifiers(&mut stats, &tower);
+                // Level up fully restores health
+                health.max = stats.get(Stat::MaxHealth).ceil();
+                health.val = health.max;
+                text_writer.send(FloatingTextEvent::level_up(position.0));
+                sound_writer.send(SoundEvent(Sound::LevelUp));
+            }
         }
     }
 }
 
-fn bullet_damage_by_level(level: usize, missile: bool) -> f32 {
-    let base = if missile { 30. } else { 1. };
-    base * (1.2f32).powf(level as f32)
+/// Damage at level 0. Level scaling is applied by `Stats` modifiers.
+fn bullet_base_damage(missile: bool) -> f32 {
+    if missile {
+        30.
+    } else {
+        1.
+    }
 }
 
-fn heal_amt_by_level(level: usize) -> f32 {
-    1. + 0.1 * level as f32
+/// Turrets unlock piercing and shotguns unlock ricochet as they level up.
+fn bullet_pierce_ricochet_by_level(level: usize, missile: bool, shotgun: bool) -> (usize, usize) {
+    if missile {
+        (0, 0)
+    } else if shotgun {
+        (0, level / 4)
+    } else {
+        (level / 3, 0)
+    }
 }
@@ -0,0 +1,153 @@
+use super::{
+    shape_from_size, tower_sprite_bundle, tower_transform_bundle, Tower, TowerBundle,
+    TowerInitBundle, TowerLevel, AMPLIFIER_HEALTH, TOWER_SIZE,
+};
+use crate::{
+    bullet::GainExpEvent,
+    simulation::SimTime,
+    stats::{Modifier, ModifierOp, ModifierSource, Stat, Stats},
+    Position, Rotation,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::{prelude::*, shapes::Circle};
+
+const AMPLIFIER_RANGE: f32 = 200.;
+/// Seconds between experience gains for the buffed towers
+const AMPLIFIER_EXP_INTERVAL: f32 = 2.;
+pub(crate) const AMPLIFIER_COLOR: Color = Color::rgb(1., 0.7, 0.3);
+
+#[derive(Component)]
+pub(crate) struct Amplifier {
+    pub cooldown: f32,
+    pub aura: Entity,
+}
+
+impl Amplifier {
+    pub(crate) fn range_by_level(level: usize) -> f32 {
+        AMPLIFIER_RANGE * (1.05f32).powf(level as f32)
+    }
+
+    /// Returns the damage and fire rate multipliers given to the towers in range
+    pub(crate) fn buff_by_level(level: usize) -> (f32, f32) {
+        (1.2 + 0.02 * level as f32, 1.15 + 0.01 * level as f32)
+    }
+}
+
+pub(crate) fn spawn_amplifier(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    position: Vec2,
+    rotation: f64,
+    bundle: TowerInitBundle,
+) -> Entity {
+    let tower = TowerBundle::new(
+        commands,
+        Position(position),
+        Rotation(rotation),
+        TOWER_SIZE,
+        TowerInitBundle {
+            health: Some(bundle.health.unwrap_or(AMPLIFIER_HEALTH)),
+            ..bundle
+        },
+    );
+    let mut sprite_bundle = tower_sprite_bundle("healer.png", asset_server, 3.);
+    sprite_bundle.sprite.color = AMPLIFIER_COLOR;
+    let sprite = commands.spawn_bundle(sprite_bundle).id();
+    let shape = commands.spawn_bundle(shape_from_size(TOWER_SIZE)).id();
+    let aura = commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &Circle {
+                radius: AMPLIFIER_RANGE,
+                center: Vec2::ZERO,
+            },
+            DrawMode::Stroke(StrokeMode::new(Color::rgba(1., 0.7, 0.3, 0.4), 2.)),
+            Transform::from_xyz(0., 0., 0.03),
+        ))
+        .id();
+    commands
+        .spawn_bundle(tower)
+        .insert(Amplifier {
+            cooldown: AMPLIFIER_EXP_INTERVAL,
+            aura,
+        })
+        .insert_bundle(tower_transform_bundle(position))
+        .add_child(sprite)
+        .add_child(shape)
+        .add_child(aura)
+        .id()
+}
+
+/// Recompute the aura modifiers of all towers from the amplifiers in range.
+///
+/// Buffs from multiple amplifiers don't stack; the strongest one for each stat is taken.
+pub(crate) fn amplifier_system(
+    time: Res<SimTime>,
+    mut query: Query<(Entity, &mut Amplifier, &TowerLevel, &Position)>,
+    mut tower_query: Query<(Entity, &Position, &mut Stats), With<Tower>>,
+    mut aura_query: Query<&mut Transform>,
+    mut exp_event: EventWriter<GainExpEvent>,
+) {
+    let delta = time.delta_seconds();
+    let mut buffs: Vec<_> = tower_query
+        .iter()
+        .map(|(entity, position, _)| (entity, position.0, 1f32, 1f32))
+        .collect();
+
+    for (entity, mut amplifier, level, position) in query.iter_mut() {
+        let range = Amplifier::range_by_level(level.level);
+        let (amp_damage, amp_fire_rate) = Amplifier::buff_by_level(level.level);
+        let mut buffed = 0;
+        for (tower, tower_position, damage, fire_rate) in buffs.iter_mut() {
+            if *tower == entity || range < tower_position.distance(position.0) {
+                continue;
+            }
+            *damage = damage.max(amp_damage);
+            *fire_rate = fire_rate.max(amp_fire_rate);
+            buffed += 1;
+        }
+
+        if let Ok(mut transform) = aura_query.get_mut(amplifier.aura) {
+            let scale = range / AMPLIFIER_RANGE;
+            transform.scale = Vec3::new(scale, scale, 1.);
+        }
+
+        if delta < amplifier.cooldown {
+            amplifier.cooldown -= delta;
+        } else {
+            amplifier.cooldown += AMPLIFIER_EXP_INTERVAL;
+            if 0 < buffed {
+                exp_event.send(GainExpEvent {
+                    entity,
+                    exp: buffed,
+                    killed: false,
+                });
+            }
+        }
+    }
+
+    for (tower, _, damage, fire_rate) in buffs {
+        if let Ok((_, _, mut stats)) = tower_query.get_mut(tower) {
+            for (stat, value) in [(Stat::Damage, damage), (Stat::FireRate, fire_rate)] {
+                let op = if value == 1. {
+                    None
+                } else {
+                    Some(ModifierOp::Mul(value))
+                };
+                // Avoid triggering change detection every frame
+                if stats.modifier(stat, ModifierSource::Aura) == op {
+                    continue;
+                }
+                if let Some(op) = op {
+                    stats.set_modifier(Modifier {
+                        stat,
+                        source: ModifierSource::Aura,
+                        op,
+                        duration: None,
+                    });
+                } else {
+                    stats.remove_modifier(stat, ModifierSource::Aura);
+                }
+            }
+        }
+    }
+}
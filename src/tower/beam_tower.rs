@@ -1,19 +1,26 @@
 use super::{
-    apprach_angle, shape_from_size, tower_sprite_bundle, tower_transform_bundle, Tower,
-    TowerBundle, TowerInitBundle, TowerLevel, BEAM_TOWER_HEALTH,
+    angle_to, apprach_angle, shape_from_size, tower_sprite_bundle, tower_transform_bundle, AimMode,
+    Targeting, Tower, TowerBundle, TowerInitBundle, BEAM_TOWER_HEALTH,
 };
 use crate::{
-    bullet::GainExpEvent, enemy::Enemy, BulletFilter, Explosion, Health, Position, Rotation,
-    StageClear, Target, Textures,
+    bullet::GainExpEvent,
+    damage::{apply_damage, Armor, DamageType, Resistances, Shield},
+    enemy::Enemy,
+    floating_text::FloatingTextEvent,
+    simulation::SimTime,
+    stats::{Stat, Stats},
+    BulletFilter, Explosion, Health, Position, Rotation, StageClear, Target, Textures,
 };
 use ::serde::{Deserialize, Serialize};
 use bevy::prelude::*;
 
-const BEAM_TOWER_SIZE: f32 = 48.;
-const BEAM_RANGE: f32 = 1000.;
+pub(crate) const BEAM_TOWER_SIZE: f32 = 48.;
+pub(crate) const BEAM_RANGE: f32 = 1000.;
 const BEAM_SPRITE_SIZE: f32 = 32.;
 const SHOOT_DURATION: f32 = 2.;
 const SHOOT_INTERVAL: f32 = 5.;
+/// Damage per second at level 0
+const BEAM_DPS: f32 = 50.;
 
 #[derive(Component, Serialize, Deserialize)]
 pub(crate) struct BeamTower {
@@ -22,6 +29,8 @@ pub(crate) struct BeamTower {
     pub filter: bool,
     #[serde(skip)]
     pub beam: Option<Entity>,
+    #[serde(skip, default = "BeamTower::default_damage_type")]
+    pub damage_type: DamageType,
 }
 
 impl BeamTower {
@@ -31,11 +40,20 @@ impl BeamTower {
             shoot_phase: 0.,
             filter: true,
             beam: Some(beam),
+            damage_type: Self::default_damage_type(),
         }
     }
 
-    pub(crate) fn beam_dps_by_level(level: usize) -> f32 {
-        50. * (1.2f32).powf(level as f32)
+    fn default_damage_type() -> DamageType {
+        DamageType::Energy
+    }
+
+    /// Start a beam unless cooling down
+    pub(crate) fn fire(&mut self) {
+        if self.cooldown == 0. {
+            self.shoot_phase = SHOOT_DURATION;
+            self.cooldown = SHOOT_INTERVAL;
+        }
     }
 }
 
@@ -55,7 +73,8 @@ pub(crate) fn spawn_beam_tower(
             health: Some(bundle.health.unwrap_or(BEAM_TOWER_HEALTH)),
             ..bundle
         },
-    );
+    )
+    .with_base_stat(Stat::Damage, BEAM_DPS);
     let sprite = commands
         .spawn_bundle(tower_sprite_bundle("beam-tower.png", asset_server, 3.))
         .id();
@@ -87,30 +106,41 @@ pub(crate) fn beam_tower_find_target(
             &Position,
             &mut BeamTower,
             &mut Target,
+            &Targeting,
+            &AimMode,
         ),
         With<Tower>,
     >,
-    mut enemy_query: Query<(Entity, &Position), With<Enemy>>,
-    time: Res<Time>,
+    mut enemy_query: Query<(Entity, &Position, Option<&Health>), With<Enemy>>,
+    time: Res<SimTime>,
 ) {
     let delta_time = time.delta_seconds();
-    for (entity, mut rotation, position, mut beamer, mut target) in query.iter_mut() {
+    for (entity, mut rotation, position, mut beamer, mut target, targeting, aim_mode) in
+        query.iter_mut()
+    {
+        // Aimed by manual_aim_system
+        if *aim_mode == AimMode::Manual {
+            continue;
+        }
         let new_target =
             enemy_query
                 .iter_mut()
-                .fold(None, |acc, (target_entity, target_position)| {
-                    if entity == target_entity {
+                .fold(None, |acc, (target_entity, target_position, health)| {
+                    if entity == target_entity
+                        || !aim_mode.allows(angle_to(position, target_position))
+                    {
                         return acc;
                     }
                     let this_dist = target_position.0.distance(position.0);
-                    if let Some((prev_dist, _, _)) = acc {
-                        if this_dist < BEAM_RANGE && this_dist < prev_dist {
-                            Some((this_dist, target_entity, target_position))
+                    let this_score = targeting.score(this_dist, health);
+                    if let Some((prev_score, _, _)) = acc {
+                        if this_dist < BEAM_RANGE && this_score < prev_score {
+                            Some((this_score, target_entity, target_position))
                         } else {
                             acc
                         }
                     } else {
-                        Some((this_dist, target_entity, target_position))
+                        Some((this_score, target_entity, target_position))
                     }
                 });
 
@@ -125,26 +155,38 @@ pub(crate) fn beam_tower_find_target(
             let (new_rotation, enabled) =
                 apprach_angle(rotation.0, target_angle, ANGLE_SPEED * delta_time as f64);
             rotation.0 = new_rotation;
-            if enabled && beamer.cooldown == 0. {
-                beamer.shoot_phase = SHOOT_DURATION;
-                beamer.cooldown = SHOOT_INTERVAL;
+            if enabled {
+                beamer.fire();
             }
+        } else if let AimMode::Hold { direction, .. } = aim_mode {
+            (rotation.0, _) =
+                apprach_angle(rotation.0, *direction, ANGLE_SPEED * delta_time as f64);
         }
     }
 }
 
 pub(crate) fn shoot_beam(
     mut commands: Commands,
-    time: Res<Time>,
+    time: Res<SimTime>,
     textures: Res<Textures>,
-    mut query: Query<(Entity, &mut BeamTower, &TowerLevel, &Position, &Rotation)>,
-    mut target_query: Query<(&Position, &mut Health, &BulletFilter)>,
+    mut query: Query<(Entity, &mut BeamTower, &Stats, &Position, &Rotation)>,
+    mut target_query: Query<(
+        Entity,
+        &Position,
+        &mut Health,
+        &BulletFilter,
+        Option<&Resistances>,
+        Option<&Armor>,
+        Option<&mut Shield>,
+    )>,
     mut beam_query: Query<&mut Visibility>,
     mut exp_event: EventWriter<GainExpEvent>,
+    mut text_writer: EventWriter<FloatingTextEvent>,
 ) {
     let delta = time.delta_seconds();
-    for (entity, mut beamer, level, position, rotation) in query.iter_mut() {
-        beamer.cooldown = (beamer.cooldown - delta).max(0.);
+    for (entity, mut beamer, stats, position, rotation) in query.iter_mut() {
+        // A higher fire rate lets the cooldown elapse faster, like with the bullet shooters
+        beamer.cooldown = (beamer.cooldown - delta * stats.get(Stat::FireRate)).max(0.);
         if delta < beamer.shoot_phase {
             beamer.shoot_phase -= delta;
         } else {
@@ -159,7 +201,16 @@ pub(crate) fn shoot_beam(
             beam.is_visible = true;
         }
 
-        for (target_position, mut target, bullet_filter) in target_query.iter_mut() {
+        for (
+            target_entity,
+            target_position,
+            mut target,
+            bullet_filter,
+            resistances,
+            armor,
+            shield,
+        ) in target_query.iter_mut()
+        {
             if target.val <= 0. || bullet_filter.filter != beamer.filter {
                 continue;
             }
@@ -179,7 +230,22 @@ pub(crate) fn shoot_beam(
                 continue;
             }
 
-            target.val = (target.val - delta * BeamTower::beam_dps_by_level(level.level)).max(0.);
+            let dealt = apply_damage(
+                &mut target,
+                (resistances, armor, shield.map(|shield| shield.into_inner())),
+                delta * stats.get(Stat::Damage),
+                beamer.damage_type,
+                delta,
+            );
+            if bullet_filter.filter {
+                // Merged into one number per target by the floating text system
+                text_writer.send(FloatingTextEvent::damage(
+                    target_entity,
+                    target_position.0,
+                    dealt,
+                ));
+            }
+            target.val = target.val.max(0.);
             if target.val == 0. {
                 exp_event.send(GainExpEvent {
                     entity,
@@ -0,0 +1,136 @@
+use super::{
+    shape_from_size, tower_sprite_bundle, tower_transform_bundle, Tower, TowerBundle,
+    TowerInitBundle, TowerLevel, CRYO_TOWER_HEALTH, TOWER_SIZE,
+};
+use crate::{
+    bullet::GainExpEvent,
+    enemy::Enemy,
+    simulation::SimTime,
+    status_effect::{StatusEffect, StatusEffectKind, StatusEffects},
+    Position, Rotation,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::{prelude::*, shapes::Circle};
+
+const CRYO_RANGE: f32 = 150.;
+/// Seconds between pulses of the aura
+const PULSE_INTERVAL: f32 = 1.;
+/// Slow lasts a bit longer than the interval so that enemies in range stay slowed
+const SLOW_DURATION: f32 = PULSE_INTERVAL * 1.2;
+pub(crate) const CRYO_TOWER_COLOR: Color = Color::rgb(0.5, 0.8, 1.);
+
+#[derive(Component)]
+pub(crate) struct CryoTower {
+    pub cooldown: f32,
+    pub aura: Entity,
+}
+
+impl CryoTower {
+    pub(crate) fn range_by_level(level: usize) -> f32 {
+        CRYO_RANGE * (1.05f32).powf(level as f32)
+    }
+
+    pub(crate) fn slow_by_level(level: usize) -> f32 {
+        (0.3 + 0.02 * level as f32).min(0.6)
+    }
+}
+
+pub(crate) fn spawn_cryo_tower(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    position: Vec2,
+    rotation: f64,
+    bundle: TowerInitBundle,
+) -> Entity {
+    let tower = TowerBundle::new(
+        commands,
+        Position(position),
+        Rotation(rotation),
+        TOWER_SIZE,
+        TowerInitBundle {
+            health: Some(bundle.health.unwrap_or(CRYO_TOWER_HEALTH)),
+            ..bundle
+        },
+    );
+    let mut sprite_bundle = tower_sprite_bundle("healer.png", asset_server, 3.);
+    sprite_bundle.sprite.color = CRYO_TOWER_COLOR;
+    let sprite = commands.spawn_bundle(sprite_bundle).id();
+    let shape = commands.spawn_bundle(shape_from_size(TOWER_SIZE)).id();
+    let aura = commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &Circle {
+                radius: CRYO_RANGE,
+                center: Vec2::ZERO,
+            },
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(Color::rgba(0.5, 0.8, 1., 0.1)),
+                outline_mode: StrokeMode::new(Color::rgba(0.5, 0.8, 1., 0.5), 2.),
+            },
+            Transform::from_xyz(0., 0., 0.03),
+        ))
+        .id();
+    commands
+        .spawn_bundle(tower)
+        .insert(CryoTower { cooldown: 0., aura })
+        .insert_bundle(tower_transform_bundle(position))
+        .add_child(sprite)
+        .add_child(shape)
+        .add_child(aura)
+        .id()
+}
+
+/// Periodically slow down all enemies within the range.
+pub(crate) fn cryo_aura_system(
+    time: Res<SimTime>,
+    mut query: Query<(Entity, &mut CryoTower, &TowerLevel, &Position), With<Tower>>,
+    mut enemy_query: Query<(&Position, &mut StatusEffects), With<Enemy>>,
+    mut exp_event: EventWriter<GainExpEvent>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut cryo_tower, level, position) in query.iter_mut() {
+        if delta < cryo_tower.cooldown {
+            cryo_tower.cooldown -= delta;
+            continue;
+        }
+        cryo_tower.cooldown += PULSE_INTERVAL;
+
+        let range = CryoTower::range_by_level(level.level);
+        let slow = CryoTower::slow_by_level(level.level);
+        let mut slowed = 0;
+        for (enemy_position, mut status_effects) in enemy_query.iter_mut() {
+            if range < enemy_position.0.distance(position.0) {
+                continue;
+            }
+            status_effects.apply(StatusEffect {
+                kind: StatusEffectKind::Slow,
+                magnitude: slow,
+                duration: SLOW_DURATION,
+                source: Some(entity),
+            });
+            slowed += 1;
+        }
+
+        if 0 < slowed {
+            exp_event.send(GainExpEvent {
+                entity,
+                exp: slowed,
+                killed: false,
+            });
+        }
+    }
+}
+
+/// Pulse the aura visual in sync with the slow application.
+pub(crate) fn cryo_pulse_system(
+    query: Query<(&CryoTower, &TowerLevel)>,
+    mut aura_query: Query<&mut Transform>,
+) {
+    for (cryo_tower, level) in query.iter() {
+        if let Ok(mut transform) = aura_query.get_mut(cryo_tower.aura) {
+            let phase = cryo_tower.cooldown / PULSE_INTERVAL;
+            let scale = CryoTower::range_by_level(level.level) / CRYO_RANGE
+                * (0.9 + 0.1 * (1. - phase).max(0.));
+            transform.scale = Vec3::new(scale, scale, 1.);
+        }
+    }
+}
@@ -1,28 +1,31 @@
 use super::{
-    heal_amt_by_level, shape_from_size, tower_sprite_bundle, tower_transform_bundle, TempEnt,
-    Timeout, Tower, TowerBundle, TowerInitBundle, HEALER_HEALTH, TOWER_SIZE,
+    shape_from_size, tower_sprite_bundle, tower_transform_bundle, TempEnt, Timeout, Tower,
+    TowerBundle, TowerInitBundle, HEALER_HEALTH, TOWER_SIZE,
 };
 use crate::{
-    bullet::GainExpEvent, tower::apprach_angle, Health, Position, Rotation, Target, Velocity,
+    bullet::GainExpEvent,
+    simulation::SimTime,
+    stats::{Stat, Stats},
+    tower::apprach_angle,
+    Health, Position, Rotation, Target, Velocity,
 };
 use bevy::prelude::*;
 
-const HEALER_RANGE: f32 = 300.;
+pub(crate) const HEALER_RANGE: f32 = 300.;
 const HEALER_INTERVAL: f32 = 2.;
+const HEAL_AMOUNT: f32 = 1.;
 
 #[derive(Component)]
 pub(crate) struct Healer {
     pub enabled: bool,
     pub cooldown: f32,
-    pub heal_amt: f32,
 }
 
 impl Healer {
-    pub(crate) fn new_with_heal_amt(heal_amt: f32) -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             enabled: false,
             cooldown: 2.,
-            heal_amt,
         }
     }
 }
@@ -34,9 +37,6 @@ pub(crate) fn spawn_healer(
     rotation: f64,
     bundle: TowerInitBundle,
 ) -> Entity {
-    let healer = Healer::new_with_heal_amt(heal_amt_by_level(
-        bundle.tower_level.as_ref().map(|l| l.level).unwrap_or(0),
-    ));
     let tower = TowerBundle::new(
         commands,
         Position(position),
@@ -46,7 +46,8 @@ pub(crate) fn spawn_healer(
             health: Some(bundle.health.unwrap_or(HEALER_HEALTH)),
             ..bundle
         },
-    );
+    )
+    .with_base_stat(Stat::HealAmount, HEAL_AMOUNT);
     let sprite = commands
         .spawn_bundle(tower_sprite_bundle("healer.png", asset_server, 3.))
         .id();
@@ -54,7 +55,7 @@ pub(crate) fn spawn_healer(
     commands
         .spawn_bundle(tower)
         .insert_bundle(tower_transform_bundle(position))
-        .insert(healer)
+        .insert(Healer::new())
         .add_child(sprite)
         .add_child(shape)
         .id()
@@ -63,7 +64,7 @@ pub(crate) fn spawn_healer(
 pub(crate) fn healer_find_target(
     mut query: Query<(Entity, &mut Rotation, &Position, &mut Healer, &mut Target), With<Tower>>,
     mut friend_query: Query<(Entity, &Position, &Health), With<Tower>>,
-    time: Res<Time>,
+    time: Res<SimTime>,
 ) {
     let delta_time = time.delta_seconds();
     for (entity, mut rotation, position, mut healer, mut target) in query.iter_mut() {
@@ -105,14 +106,15 @@ pub(crate) fn healer_find_target(
 
 pub(crate) fn heal_target(
     mut commands: Commands,
-    time: Res<Time>,
+    time: Res<SimTime>,
     asset_server: Res<AssetServer>,
-    mut query: Query<(Entity, &mut Healer, &Target, &Position)>,
+    mut query: Query<(Entity, &mut Healer, &Stats, &Target, &Position)>,
     mut target_query: Query<(&Position, &mut Health)>,
     mut exp_event: EventWriter<GainExpEvent>,
 ) {
     let delta = time.delta_seconds();
-    for (entity, mut healer, target, position) in query.iter_mut() {
+    for (entity, mut healer, stats, target, position) in query.iter_mut() {
+        let heal_amt = stats.get(Stat::HealAmount);
         if !healer.enabled {
             continue;
         }
@@ -124,11 +126,11 @@ pub(crate) fn heal_target(
         if let Some(target) = target.0 {
             if let Ok((target_position, mut target)) = target_query.get_mut(target) {
                 if target.val < target.max {
-                    target.val += healer.heal_amt;
+                    target.val += heal_amt;
                     healer.cooldown += HEALER_INTERVAL;
                     exp_event.send(GainExpEvent {
                         entity,
-                        exp: (3. * healer.heal_amt).ceil() as usize,
+                        exp: (3. * heal_amt).ceil() as usize,
                         killed: false,
                     });
                     commands
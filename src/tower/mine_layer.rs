@@ -0,0 +1,199 @@
+use super::{
+    shape_from_size, tower_sprite_bundle, tower_transform_bundle, Tower, TowerBundle,
+    TowerInitBundle, TowerLevel, MINE_LAYER_HEALTH, TOWER_SIZE,
+};
+use crate::{
+    arena::ArenaBounds,
+    bullet::SplashEvent,
+    damage::DamageType,
+    enemy::Enemy,
+    map::CurrentMap,
+    simulation::{GameRng, SimTime},
+    stats::{Stat, Stats},
+    BulletFilter, Position, Rotation, StageClear,
+};
+use bevy::prelude::*;
+use bevy_prototype_lyon::{prelude::*, shapes::Circle};
+use rand::Rng;
+
+const MINE_INTERVAL: f32 = 3.;
+/// Mines are dropped at a random position within this distance from the tower
+pub(crate) const MINE_DROP_RANGE: f32 = 150.;
+const MINE_TRIGGER_RADIUS: f32 = 30.;
+const MINE_SPLASH_RADIUS: f32 = 80.;
+const MINE_LIFETIME: f32 = 20.;
+/// Damage at level 0
+const MINE_DAMAGE: f32 = 40.;
+/// Seconds after being dropped until the mine can be triggered
+const MINE_ARM_DELAY: f32 = 0.5;
+pub(crate) const MINE_LAYER_COLOR: Color = Color::rgb(0.7, 1., 0.6);
+
+#[derive(Component)]
+pub(crate) struct MineLayer {
+    pub cooldown: f32,
+}
+
+impl MineLayer {
+    pub(crate) fn max_mines_by_level(level: usize) -> usize {
+        4 + level / 3
+    }
+}
+
+#[derive(Component)]
+pub(crate) struct Mine {
+    owner: Entity,
+    damage: f32,
+    lifetime: f32,
+}
+
+pub(crate) fn spawn_mine_layer(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    position: Vec2,
+    rotation: f64,
+    bundle: TowerInitBundle,
+) -> Entity {
+    let tower = TowerBundle::new(
+        commands,
+        Position(position),
+        Rotation(rotation),
+        TOWER_SIZE,
+        TowerInitBundle {
+            health: Some(bundle.health.unwrap_or(MINE_LAYER_HEALTH)),
+            ..bundle
+        },
+    )
+    .with_base_stat(Stat::Damage, MINE_DAMAGE);
+    let mut sprite_bundle = tower_sprite_bundle("missile-tower.png", asset_server, 2.);
+    sprite_bundle.sprite.color = MINE_LAYER_COLOR;
+    let sprite = commands.spawn_bundle(sprite_bundle).id();
+    let shape = commands.spawn_bundle(shape_from_size(TOWER_SIZE)).id();
+    commands
+        .spawn_bundle(tower)
+        .insert(MineLayer {
+            cooldown: MINE_INTERVAL,
+        })
+        .insert_bundle(tower_transform_bundle(position))
+        .add_child(sprite)
+        .add_child(shape)
+        .id()
+}
+
+fn spawn_mine(commands: &mut Commands, owner: Entity, position: Vec2, damage: f32) {
+    let trigger = commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &Circle {
+                radius: MINE_TRIGGER_RADIUS,
+                center: Vec2::ZERO,
+            },
+            DrawMode::Stroke(StrokeMode::new(Color::rgba(1., 0.3, 0.2, 0.3), 1.)),
+            Transform::default(),
+        ))
+        .id();
+    commands
+        .spawn_bundle(GeometryBuilder::build_as(
+            &Circle {
+                radius: 6.,
+                center: Vec2::ZERO,
+            },
+            DrawMode::Outlined {
+                fill_mode: FillMode::color(Color::rgb(0.8, 0.2, 0.1)),
+                outline_mode: StrokeMode::new(Color::rgb(0.3, 0.3, 0.3), 2.),
+            },
+            Transform::from_xyz(position.x, position.y, 0.05),
+        ))
+        .insert(Position(position))
+        .insert(Mine {
+            owner,
+            damage,
+            lifetime: MINE_LIFETIME,
+        })
+        .insert(StageClear)
+        .add_child(trigger);
+}
+
+/// Drop mines around the tower as long as it has less than the maximum number of active mines.
+pub(crate) fn mine_layer_system(
+    mut commands: Commands,
+    time: Res<SimTime>,
+    arena: Res<ArenaBounds>,
+    current_map: Res<CurrentMap>,
+    mut rng: ResMut<GameRng>,
+    mut query: Query<(Entity, &mut MineLayer, &TowerLevel, &Stats, &Position), With<Tower>>,
+    mine_query: Query<&Mine>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut mine_layer, level, stats, position) in query.iter_mut() {
+        let delta = delta * stats.get(Stat::FireRate);
+        if delta < mine_layer.cooldown {
+            mine_layer.cooldown -= delta;
+            continue;
+        }
+
+        let active = mine_query
+            .iter()
+            .filter(|mine| mine.owner == entity)
+            .count();
+        if MineLayer::max_mines_by_level(level.level) <= active {
+            continue;
+        }
+
+        // Give up after a few attempts if the tower is surrounded by blocking terrain
+        let drop_position = (0..10).find_map(|_| {
+            let angle = rng.gen::<f32>() * std::f32::consts::PI * 2.;
+            let dist = (0.3 + 0.7 * rng.gen::<f32>()) * MINE_DROP_RANGE;
+            let candidate = position.0 + Vec2::new(angle.cos(), angle.sin()) * dist;
+            if arena.contains(candidate) && !current_map.spec().is_blocked(candidate) {
+                Some(candidate)
+            } else {
+                None
+            }
+        });
+
+        if let Some(drop_position) = drop_position {
+            spawn_mine(
+                &mut commands,
+                entity,
+                drop_position,
+                stats.get(Stat::Damage),
+            );
+            mine_layer.cooldown += MINE_INTERVAL;
+        }
+    }
+}
+
+/// Detonate mines when an enemy comes within the trigger radius, and remove expired ones.
+pub(crate) fn mine_trigger_system(
+    mut commands: Commands,
+    time: Res<SimTime>,
+    mut query: Query<(Entity, &mut Mine, &Position)>,
+    enemy_query: Query<(&Position, &BulletFilter), With<Enemy>>,
+    mut splash_writer: EventWriter<SplashEvent>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut mine, position) in query.iter_mut() {
+        mine.lifetime -= delta;
+        if mine.lifetime < 0. {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        if MINE_LIFETIME - MINE_ARM_DELAY < mine.lifetime {
+            continue;
+        }
+
+        let triggered = enemy_query.iter().any(|(enemy_position, bullet_filter)| {
+            enemy_position.0.distance(position.0) < MINE_TRIGGER_RADIUS + bullet_filter.radius
+        });
+        if triggered {
+            splash_writer.send(SplashEvent {
+                position: position.0,
+                radius: MINE_SPLASH_RADIUS,
+                damage: mine.damage,
+                damage_type: DamageType::Explosive,
+                filter: true,
+                owner: mine.owner,
+            });
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -1,22 +1,40 @@
+mod boss_health;
 mod difficulty_select;
 mod pause;
 mod quit;
 mod scoreboard;
+mod settings_menu;
+mod start_button;
+mod tower_list;
 mod tower_palette;
 mod tower_status;
+mod undo_button;
 
 use bevy::{ecs::system::EntityCommands, prelude::*};
 
 use self::{
+    boss_health::build_boss_health,
     difficulty_select::{add_difficulty_buttons, DifficultySelectPlugin},
-    pause::{add_pause_button, pause_button_system, pause_event_system, show_pause_button_system},
+    pause::{
+        add_pause_button, pause_button_system, pause_event_system, pause_key_system,
+        show_pause_button_system,
+    },
     quit::{add_quit_button, quit_button_system, quit_event_system, show_quit_button_system},
     scoreboard::{add_scoreboard, update_credits, update_level, update_scoreboard},
+    settings_menu::build_settings_menu,
+    start_button::build_start_button,
+    tower_list::build_tower_list,
     tower_palette::{add_palette_buttons, build_tower_palette},
     tower_status::build_tower_status,
+    undo_button::build_undo_button,
+};
+use crate::{
+    command::apply_player_commands,
+    replay::{Playback, SeekReplayEvent},
+    Level,
 };
-use crate::Level;
 pub(crate) use pause::not_paused;
+pub(crate) use tower_palette::{TowerPalette, TowerTypeQuery};
 
 pub(crate) struct UIPlugin;
 
@@ -28,30 +46,43 @@ impl Plugin for UIPlugin {
         app.add_event::<PauseEvent>();
         app.add_startup_system(build_ui);
         app.add_system(update_progress_bar);
+        app.add_system(progress_bar_seek_system);
         app.add_system(update_level);
         app.add_system(update_scoreboard);
         app.add_system(update_credits);
         build_tower_status(app);
         build_tower_palette(app);
-        app.add_system(quit_event_system);
+        build_boss_health(app);
+        build_tower_list(app);
+        build_settings_menu(app);
+        build_undo_button(app);
+        build_start_button(app);
+        // Quitting and pausing take effect in the frame of the command, so that the stage stops
+        // at the same step in the replays
+        app.add_system(quit_event_system.after(apply_player_commands));
         app.add_system(quit_button_system);
         app.add_system(show_quit_button_system);
         app.insert_resource(PauseState(false));
-        app.add_system(pause_event_system);
+        app.add_system(pause_event_system.after(apply_player_commands));
         app.add_system(pause_button_system);
+        app.add_system(pause_key_system);
         app.add_system(show_pause_button_system);
     }
 }
 
 struct StartEvent(usize);
-struct QuitEvent;
-struct PauseEvent;
+pub(crate) struct QuitEvent;
+pub(crate) struct PauseEvent;
 
 pub(crate) struct PauseState(bool);
 
 #[derive(Component)]
 struct ProgressBar;
 
+/// The frame of the progress bar, which seeks the replay being played back when clicked
+#[derive(Component)]
+struct ProgressBarFrame;
+
 const SCOREBOARD_FONT_SIZE: f32 = 40.0;
 const PADDING: f32 = 5.;
 const PADDING_PX: Val = Val::Px(PADDING);
@@ -85,6 +116,8 @@ fn build_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
             color: Color::rgb(0.4, 0.4, 1.0).into(),
             ..default()
         })
+        .insert(Interaction::default())
+        .insert(ProgressBarFrame)
         .with_children(|parent| {
             parent
                 .spawn_bundle(NodeBundle {
@@ -115,6 +148,34 @@ fn update_progress_bar(level: Res<Level>, mut query: Query<&mut Style, With<Prog
     }
 }
 
+fn progress_bar_seek_system(
+    windows: Res<Windows>,
+    level: Res<Level>,
+    playback: Res<Playback>,
+    query: Query<
+        (&Interaction, &Node, &GlobalTransform),
+        (Changed<Interaction>, With<ProgressBarFrame>),
+    >,
+    mut writer: EventWriter<SeekReplayEvent>,
+) {
+    let duration = match (level.as_ref(), playback.is_playing()) {
+        (Level::Running { timer, .. }, true) => timer.duration().as_secs_f32(),
+        _ => return,
+    };
+    let cursor = if let Some(cursor) = windows.get_primary().and_then(|w| w.cursor_position()) {
+        cursor
+    } else {
+        return;
+    };
+    for (interaction, node, transform) in query.iter() {
+        if *interaction == Interaction::Clicked {
+            let left = transform.translation.x - node.size.x / 2.;
+            let fraction = ((cursor.x - left) / node.size.x).clamp(0., 1.);
+            writer.send(SeekReplayEvent(fraction * duration));
+        }
+    }
+}
+
 /// A helper function to add a text component bundle with a variable number of text sections.
 ///
 /// This function assumes the first section of the `text` is a section title, so it has bold style
@@ -0,0 +1,111 @@
+use bevy::prelude::*;
+
+use crate::{enemy::Boss, Health};
+
+use super::{spawn_text, PADDING_PX};
+
+const BOSS_BAR_WIDTH: f32 = 400.;
+
+#[derive(Component)]
+struct BossHealthPanel;
+
+#[derive(Component)]
+struct BossNameText;
+
+#[derive(Component)]
+struct BossHealthBar;
+
+pub(super) fn build_boss_health(app: &mut App) {
+    app.add_startup_system(add_boss_health_panel);
+    app.add_system(update_boss_health);
+}
+
+fn add_boss_health_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Auto),
+                justify_content: JustifyContent::Center,
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: PADDING_PX,
+                    ..default()
+                },
+                ..default()
+            },
+            color: Color::NONE.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        flex_direction: FlexDirection::ColumnReverse,
+                        padding: Rect::all(Val::Px(2.)),
+                        ..default()
+                    },
+                    visibility: Visibility { is_visible: false },
+                    color: Color::rgba(0., 0., 0., 0.5).into(),
+                    ..default()
+                })
+                .insert(BossHealthPanel)
+                .with_children(|parent| {
+                    spawn_text(&asset_server, parent, &["Boss: ", ""], |mut parent| {
+                        parent.insert(BossNameText).insert(BossHealthPanel);
+                    });
+
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(BOSS_BAR_WIDTH), Val::Px(12.)),
+                                border: Rect::all(Val::Px(2.)),
+                                ..default()
+                            },
+                            visibility: Visibility { is_visible: false },
+                            color: Color::rgb(0.4, 0.1, 0.1).into(),
+                            ..default()
+                        })
+                        .insert(BossHealthPanel)
+                        .with_children(|parent| {
+                            parent
+                                .spawn_bundle(NodeBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                                        ..default()
+                                    },
+                                    visibility: Visibility { is_visible: false },
+                                    color: Color::rgb(1., 0.2, 0.2).into(),
+                                    ..default()
+                                })
+                                .insert(BossHealthPanel)
+                                .insert(BossHealthBar);
+                        });
+                });
+        });
+}
+
+/// Show the health of the first boss alive, or hide the panel if there is none.
+fn update_boss_health(
+    query_boss: Query<(&Boss, &Health)>,
+    mut query_visible: Query<&mut Visibility, With<BossHealthPanel>>,
+    mut query_name: Query<&mut Text, With<BossNameText>>,
+    mut query_bar: Query<&mut Style, With<BossHealthBar>>,
+) {
+    let boss = query_boss.iter().next();
+
+    for mut visibility in query_visible.iter_mut() {
+        if visibility.is_visible != boss.is_some() {
+            visibility.is_visible = boss.is_some();
+        }
+    }
+
+    if let Some((boss, health)) = boss {
+        if let Ok(mut text) = query_name.get_single_mut() {
+            text.sections[1].value = boss.spec.name.to_string();
+        }
+        if let Ok(mut style) = query_bar.get_single_mut() {
+            style.size.width = Val::Percent((health.val / health.max).max(0.) * 100.);
+        }
+    }
+}
@@ -1,11 +1,15 @@
 use bevy::{prelude::*, ui::FocusPolicy};
 
 use crate::{
+    arena::ArenaBounds,
+    command::placement_error,
+    map::{CurrentMap, MAP_SPECS},
+    replay::WatchReplayEvent,
     tower::{spawn_towers, Tower},
-    Level, Scoreboard, StageClear, MAX_DIFFICULTY,
+    Level, Position, Scoreboard, StageClear, MAX_DIFFICULTY,
 };
 
-use super::{quit::HOVERED_BUTTON, StartEvent, TEXT_COLOR};
+use super::{quit::HOVERED_BUTTON, settings_menu::SettingsMenuButton, StartEvent, TEXT_COLOR};
 
 const DIFFICULTY_FONT_SIZE: f32 = 32.0;
 const HIGHSCORE_FONT_SIZE: f32 = 16.0;
@@ -19,6 +23,9 @@ impl Plugin for DifficultySelectPlugin {
         app.add_system(show_difficulty_buttons_system);
         app.add_system(cleared_icon_system);
         app.add_system(high_score_text_system);
+        app.add_system(map_button_system);
+        app.add_system(map_name_text_system);
+        app.add_system(watch_replay_button_system);
     }
 }
 
@@ -37,6 +44,17 @@ struct DifficultyCleared(usize);
 #[derive(Component)]
 struct HighScoreText(usize);
 
+#[derive(Component)]
+struct MapSelectButton;
+
+#[derive(Component)]
+struct MapNameText;
+
+#[derive(Component)]
+struct WatchReplayButton;
+
+const MAP_BUTTON_COLOR: Color = Color::rgb(0.15, 0.15, 0.3);
+
 pub(super) fn add_difficulty_buttons(commands: &mut Commands, asset_server: &Res<AssetServer>) {
     commands
         .spawn_bundle(NodeBundle {
@@ -52,6 +70,100 @@ pub(super) fn add_difficulty_buttons(commands: &mut Commands, asset_server: &Res
         })
         .insert(DifficultyButtonFilter)
         .with_children(|parent| {
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(300.0), Val::Px(50.0)),
+                        margin: Rect::all(Val::Px(3.)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    color: MAP_BUTTON_COLOR.into(),
+                    ..default()
+                })
+                .insert(MapSelectButton)
+                .insert(DifficultyButtonFilter)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                "Map: ?",
+                                TextStyle {
+                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                    font_size: DIFFICULTY_FONT_SIZE,
+                                    color: TEXT_COLOR,
+                                },
+                                Default::default(),
+                            ),
+                            ..default()
+                        })
+                        .insert(MapNameText)
+                        .insert(DifficultyButtonFilter);
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(300.0), Val::Px(50.0)),
+                        margin: Rect::all(Val::Px(3.)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    color: MAP_BUTTON_COLOR.into(),
+                    ..default()
+                })
+                .insert(SettingsMenuButton)
+                .insert(DifficultyButtonFilter)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                "Settings",
+                                TextStyle {
+                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                    font_size: DIFFICULTY_FONT_SIZE,
+                                    color: TEXT_COLOR,
+                                },
+                                Default::default(),
+                            ),
+                            ..default()
+                        })
+                        .insert(DifficultyButtonFilter);
+                });
+
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(300.0), Val::Px(50.0)),
+                        margin: Rect::all(Val::Px(3.)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    color: MAP_BUTTON_COLOR.into(),
+                    ..default()
+                })
+                .insert(WatchReplayButton)
+                .insert(DifficultyButtonFilter)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                "Watch last run",
+                                TextStyle {
+                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                    font_size: DIFFICULTY_FONT_SIZE,
+                                    color: TEXT_COLOR,
+                                },
+                                Default::default(),
+                            ),
+                            ..default()
+                        })
+                        .insert(DifficultyButtonFilter);
+                });
+
             for difficulty in 0..MAX_DIFFICULTY {
                 let color = Color::rgb(
                     0.15 + difficulty as f32 / MAX_DIFFICULTY as f32 * 0.5,
@@ -146,7 +258,7 @@ fn difficulty_event_system(
         for entity in query.iter() {
             commands.entity(entity).despawn_recursive();
         }
-        *level = Level::start(event.0);
+        *level = Level::build(event.0);
         scoreboard.score = 0.;
 
         let towers = query_towers.iter().count();
@@ -240,3 +352,84 @@ fn high_score_text_system(
         };
     }
 }
+
+/// Clicking the map button cycles through the available maps.
+///
+/// The maps where the current towers would stand outside the buildable area are skipped.
+fn map_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<Button>, With<MapSelectButton>),
+    >,
+    level: Res<Level>,
+    arena: Res<ArenaBounds>,
+    mut current_map: ResMut<CurrentMap>,
+    query_towers: Query<(&Position, &Tower)>,
+) {
+    if let Level::Select = level.as_ref() {
+        for (interaction, mut color) in interaction_query.iter_mut() {
+            match *interaction {
+                Interaction::Clicked => {
+                    // The towers don't move with the map, so only the map can make them invalid
+                    let fits = |map: &CurrentMap| {
+                        query_towers.iter().all(|(position, tower)| {
+                            placement_error(&arena, map, std::iter::empty(), position.0, tower.size)
+                                .is_none()
+                        })
+                    };
+                    if let Some(next) = (1..MAP_SPECS.len())
+                        .map(|offset| CurrentMap((current_map.0 + offset) % MAP_SPECS.len()))
+                        .find(|map| fits(map))
+                    {
+                        *current_map = next;
+                    } else {
+                        println!("No other map fits the current towers");
+                    }
+                }
+                Interaction::Hovered => {
+                    *color = HOVERED_BUTTON.into();
+                }
+                Interaction::None => {
+                    *color = MAP_BUTTON_COLOR.into();
+                }
+            }
+        }
+    }
+}
+
+fn watch_replay_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<Button>, With<WatchReplayButton>),
+    >,
+    level: Res<Level>,
+    mut writer: EventWriter<WatchReplayEvent>,
+) {
+    if let Level::Select = level.as_ref() {
+        for (interaction, mut color) in interaction_query.iter_mut() {
+            match *interaction {
+                Interaction::Clicked => {
+                    writer.send(WatchReplayEvent);
+                }
+                Interaction::Hovered => {
+                    *color = HOVERED_BUTTON.into();
+                }
+                Interaction::None => {
+                    *color = MAP_BUTTON_COLOR.into();
+                }
+            }
+        }
+    }
+}
+
+fn map_name_text_system(
+    mut query: Query<&mut Text, With<MapNameText>>,
+    current_map: Res<CurrentMap>,
+) {
+    if !current_map.is_changed() {
+        return;
+    }
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!("Map: {}", current_map.spec().name);
+    }
+}
@@ -1,12 +1,18 @@
 use bevy::{ecs::schedule::ShouldRun, prelude::*};
 
-use crate::Level;
+use crate::{
+    command::PlayerCommand,
+    settings::{KeyAction, Settings},
+    Level,
+};
 
 use super::{
     PauseEvent, PauseState, BUTTON_HEIGHT, PADDING_PX, PADDING_PX2, SCOREBOARD_FONT_SIZE,
     TEXT_COLOR,
 };
 
+pub(super) const PAUSE_BUTTON_WIDTH: f32 = 100.;
+
 const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
 const ACTIVE_BUTTON: Color = Color::rgb(0.40, 0.40, 0.15);
 const HOVERED_BUTTON: Color = Color::rgb(0.25, 0.25, 0.25);
@@ -19,7 +25,7 @@ pub(super) fn add_pause_button(commands: &mut Commands, asset_server: &Res<Asset
     commands
         .spawn_bundle(ButtonBundle {
             style: Style {
-                size: Size::new(Val::Px(100.0), Val::Px(BUTTON_HEIGHT)),
+                size: Size::new(Val::Px(PAUSE_BUTTON_WIDTH), Val::Px(BUTTON_HEIGHT)),
                 margin: Rect::all(Val::Auto),
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
@@ -58,7 +64,7 @@ pub(super) fn pause_button_system(
         (&Interaction, &mut UiColor),
         (Changed<Interaction>, With<Button>, With<PauseButtonFilter>),
     >,
-    mut writer: EventWriter<PauseEvent>,
+    mut writer: EventWriter<PlayerCommand>,
     level: Res<Level>,
     pause_state: Res<PauseState>,
 ) {
@@ -68,7 +74,7 @@ pub(super) fn pause_button_system(
     for (interaction, mut color) in interaction_query.iter_mut() {
         match *interaction {
             Interaction::Clicked => {
-                writer.send(PauseEvent);
+                writer.send(PlayerCommand::Pause);
             }
             Interaction::Hovered => {
                 *color = if pause_state.0 {
@@ -88,6 +94,20 @@ pub(super) fn pause_button_system(
     }
 }
 
+pub(super) fn pause_key_system(
+    keys: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    mut writer: EventWriter<PlayerCommand>,
+    level: Res<Level>,
+) {
+    if let Level::Select = level.as_ref() {
+        return;
+    }
+    if settings.key_bindings.just_pressed(&keys, KeyAction::Pause) {
+        writer.send(PlayerCommand::Pause);
+    }
+}
+
 pub(super) fn pause_event_system(
     mut reader: EventReader<PauseEvent>,
     mut pause_state: ResMut<PauseState>,
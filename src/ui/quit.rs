@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::{ClearEvent, Level, StageClear};
+use crate::{command::PlayerCommand, replay::Playback, ClearEvent, Level, StageClear};
 
 use super::{QuitEvent, BUTTON_HEIGHT, PADDING_PX, SCOREBOARD_FONT_SIZE, TEXT_COLOR};
 
@@ -55,16 +55,22 @@ pub(super) fn quit_button_system(
         (&Interaction, &mut UiColor),
         (Changed<Interaction>, With<Button>, With<QuitButtonFilter>),
     >,
-    mut writer: EventWriter<QuitEvent>,
+    mut writer: EventWriter<PlayerCommand>,
+    mut quit_writer: EventWriter<QuitEvent>,
     level: Res<Level>,
+    playback: Res<Playback>,
 ) {
     if let Level::Select = level.as_ref() {
         return;
     }
     for (interaction, mut color) in interaction_query.iter_mut() {
         match *interaction {
+            // Quitting a replay only stops watching it, without a command in the played back run
+            Interaction::Clicked if playback.is_playing() => {
+                quit_writer.send(QuitEvent);
+            }
             Interaction::Clicked => {
-                writer.send(QuitEvent);
+                writer.send(PlayerCommand::Quit);
             }
             Interaction::Hovered => {
                 *color = HOVERED_BUTTON.into();
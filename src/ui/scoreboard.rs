@@ -119,10 +119,11 @@ pub(super) fn add_scoreboard(commands: &mut Commands, asset_server: &Res<AssetSe
 
 pub(super) fn update_level(level: Res<Level>, mut query: Query<&mut Text, With<LevelText>>) {
     if let Ok(mut text) = query.get_single_mut() {
-        text.sections[1].value = if let Level::Running { difficulty, .. } = level.as_ref() {
-            format!("{}", difficulty)
-        } else {
-            "-".to_string()
+        text.sections[1].value = match level.as_ref() {
+            Level::Build { difficulty, .. } | Level::Running { difficulty, .. } => {
+                format!("{}", difficulty)
+            }
+            Level::Select => "-".to_string(),
         }
     }
 }
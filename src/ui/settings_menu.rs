@@ -0,0 +1,465 @@
+use bevy::{prelude::*, ui::FocusPolicy};
+
+use crate::{
+    settings::{KeyAction, Settings},
+    Level,
+};
+
+use super::{
+    quit::HOVERED_BUTTON, PauseState, BUTTON_HEIGHT, PADDING, PADDING_PX2, STATUS_FONT_SIZE,
+    TEXT_COLOR,
+};
+
+const MENU_FONT_SIZE: f32 = 24.;
+const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+const REBINDING_BUTTON: Color = Color::rgb(0.40, 0.40, 0.15);
+const VOLUME_STEP: f32 = 0.1;
+const UI_SCALE_STEP: f32 = 0.25;
+const UI_SCALE_RANGE: (f32, f32) = (0.5, 2.);
+
+/// Whether the menu is shown, and the action waiting for a key press to bind
+#[derive(Default)]
+struct SettingsMenu {
+    open: bool,
+    rebinding: Option<KeyAction>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SettingsItem {
+    MasterVolume,
+    SfxVolume,
+    MusicVolume,
+    DamageNumbers,
+    HealthBars,
+    UiScale,
+    Fullscreen,
+    SnapToGrid,
+}
+
+impl SettingsItem {
+    const ALL: [Self; 8] = [
+        Self::MasterVolume,
+        Self::SfxVolume,
+        Self::MusicVolume,
+        Self::DamageNumbers,
+        Self::HealthBars,
+        Self::UiScale,
+        Self::Fullscreen,
+        Self::SnapToGrid,
+    ];
+
+    fn text(&self, settings: &Settings) -> String {
+        let on_off = |b: bool| if b { "On" } else { "Off" };
+        match self {
+            Self::MasterVolume => format!("Master volume: {:.0}%", settings.master_volume * 100.),
+            Self::SfxVolume => format!("Effects volume: {:.0}%", settings.sfx_volume * 100.),
+            Self::MusicVolume => format!("Music volume: {:.0}%", settings.music_volume * 100.),
+            Self::DamageNumbers => format!("Damage numbers: {}", on_off(settings.damage_numbers)),
+            Self::HealthBars => format!("Health bars: {}", on_off(settings.health_bars)),
+            Self::UiScale => format!("UI scale: {:.0}%", settings.ui_scale * 100.),
+            Self::Fullscreen => format!("Fullscreen: {}", on_off(settings.fullscreen)),
+            Self::SnapToGrid => format!("Snap to grid: {}", on_off(settings.snap_to_grid)),
+        }
+    }
+
+    /// Step the value up or down. Toggles flip on either direction.
+    fn step(&self, settings: &mut Settings, up: bool) {
+        let sign = if up { 1. } else { -1. };
+        let volume = |v: &mut f32| *v = (*v + sign * VOLUME_STEP).clamp(0., 1.);
+        match self {
+            Self::MasterVolume => volume(&mut settings.master_volume),
+            Self::SfxVolume => volume(&mut settings.sfx_volume),
+            Self::MusicVolume => volume(&mut settings.music_volume),
+            Self::DamageNumbers => settings.damage_numbers = !settings.damage_numbers,
+            Self::HealthBars => settings.health_bars = !settings.health_bars,
+            Self::UiScale => {
+                settings.ui_scale = (settings.ui_scale + sign * UI_SCALE_STEP)
+                    .clamp(UI_SCALE_RANGE.0, UI_SCALE_RANGE.1)
+            }
+            Self::Fullscreen => settings.fullscreen = !settings.fullscreen,
+            Self::SnapToGrid => settings.snap_to_grid = !settings.snap_to_grid,
+        }
+    }
+}
+
+/// Opens the settings menu. Lives in the difficulty select screen and the pause screen.
+#[derive(Component)]
+pub(super) struct SettingsMenuButton;
+
+/// The button shown next to the pause button while paused
+#[derive(Component)]
+struct PauseSettingsButton;
+
+#[derive(Component)]
+struct SettingsPanel;
+
+#[derive(Component)]
+struct SettingsStepButton {
+    item: SettingsItem,
+    up: bool,
+}
+
+#[derive(Component)]
+struct SettingsItemText(SettingsItem);
+
+#[derive(Component)]
+struct KeyBindingButton(KeyAction);
+
+#[derive(Component)]
+struct CloseSettingsButton;
+
+/// The font size a UI text was made with, so that the UI scale doesn't compound
+#[derive(Component)]
+struct BaseFontSize(Vec<f32>);
+
+pub(super) fn build_settings_menu(app: &mut App) {
+    app.insert_resource(SettingsMenu::default());
+    app.add_startup_system(add_settings_menu);
+    app.add_system(open_settings_system);
+    app.add_system(show_settings_menu_system);
+    app.add_system(settings_step_system);
+    app.add_system(key_binding_button_system);
+    app.add_system(rebind_key_system);
+    app.add_system(close_settings_system);
+    app.add_system(update_settings_text);
+    app.add_system(ui_scale_system);
+}
+
+fn text_style(asset_server: &AssetServer, font_size: f32) -> TextStyle {
+    TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size,
+        color: TEXT_COLOR,
+    }
+}
+
+fn spawn_button(
+    parent: &mut ChildBuilder,
+    asset_server: &AssetServer,
+    label: &str,
+    components: impl FnOnce(&mut bevy::ecs::system::EntityCommands),
+) {
+    let mut builder = parent.spawn_bundle(ButtonBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(2.)),
+            padding: Rect::all(Val::Px(4.)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        color: NORMAL_BUTTON.into(),
+        ..default()
+    });
+    components(&mut builder);
+    builder.with_children(|parent| {
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section(label, text_style(asset_server, MENU_FONT_SIZE), default()),
+            focus_policy: FocusPolicy::Pass,
+            ..default()
+        });
+    });
+}
+
+fn add_settings_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(100.0), Val::Px(BUTTON_HEIGHT)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    // Below the pause button
+                    top: Val::Px(PADDING * 2. + BUTTON_HEIGHT),
+                    right: PADDING_PX2 + super::quit::BUTTON_WIDTH,
+                    ..default()
+                },
+                display: Display::None,
+                ..default()
+            },
+            color: NORMAL_BUTTON.into(),
+            ..default()
+        })
+        .insert(SettingsMenuButton)
+        .insert(PauseSettingsButton)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "Settings",
+                    text_style(&asset_server, STATUS_FONT_SIZE),
+                    default(),
+                ),
+                focus_policy: FocusPolicy::Pass,
+                ..default()
+            });
+        });
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                margin: Rect::all(Val::Auto),
+                align_items: AlignItems::Stretch,
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: Rect::all(Val::Px(10.)),
+                display: Display::None,
+                ..default()
+            },
+            color: Color::rgba(0.1, 0.1, 0.1, 0.95).into(),
+            ..default()
+        })
+        .insert(SettingsPanel)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "Settings",
+                    text_style(&asset_server, MENU_FONT_SIZE * 1.5),
+                    default(),
+                ),
+                ..default()
+            });
+
+            for item in SettingsItem::ALL {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        color: Color::NONE.into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        spawn_button(parent, &asset_server, "<", |builder| {
+                            builder.insert(SettingsStepButton { item, up: false });
+                        });
+                        spawn_button(parent, &asset_server, ">", |builder| {
+                            builder.insert(SettingsStepButton { item, up: true });
+                        });
+                        parent
+                            .spawn_bundle(TextBundle {
+                                text: Text::with_section(
+                                    "",
+                                    text_style(&asset_server, MENU_FONT_SIZE),
+                                    default(),
+                                ),
+                                ..default()
+                            })
+                            .insert(SettingsItemText(item));
+                    });
+            }
+
+            for action in KeyAction::ALL {
+                spawn_button(parent, &asset_server, "", |builder| {
+                    builder.insert(KeyBindingButton(action));
+                });
+            }
+
+            spawn_button(parent, &asset_server, "Close", |builder| {
+                builder.insert(CloseSettingsButton);
+            });
+        });
+}
+
+/// The menu can be opened from the difficulty select screen or while paused.
+fn can_open(level: &Level, pause_state: &PauseState) -> bool {
+    matches!(level, Level::Select) || pause_state.0
+}
+
+fn open_settings_system(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<SettingsMenuButton>)>,
+    level: Res<Level>,
+    pause_state: Res<PauseState>,
+    mut menu: ResMut<SettingsMenu>,
+) {
+    if !can_open(&level, &pause_state) {
+        return;
+    }
+    if interaction_query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Clicked)
+    {
+        menu.open = true;
+    }
+}
+
+fn show_settings_menu_system(
+    level: Res<Level>,
+    pause_state: Res<PauseState>,
+    mut menu: ResMut<SettingsMenu>,
+    mut query_panel: Query<&mut Style, (With<SettingsPanel>, Without<PauseSettingsButton>)>,
+    mut query_pause_button: Query<&mut Style, With<PauseSettingsButton>>,
+) {
+    // Resuming the game closes the menu
+    if menu.open && !can_open(&level, &pause_state) {
+        menu.open = false;
+        menu.rebinding = None;
+    }
+
+    let display = |shown: bool| {
+        if shown {
+            Display::Flex
+        } else {
+            Display::None
+        }
+    };
+    // Avoid triggering the layout every frame
+    for mut style in query_panel.iter_mut() {
+        if style.display != display(menu.open) {
+            style.display = display(menu.open);
+        }
+    }
+    let pause_button = pause_state.0 && !matches!(level.as_ref(), Level::Select);
+    for mut style in query_pause_button.iter_mut() {
+        if style.display != display(pause_button) {
+            style.display = display(pause_button);
+        }
+    }
+}
+
+fn settings_step_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor, &SettingsStepButton),
+        Changed<Interaction>,
+    >,
+    mut settings: ResMut<Settings>,
+) {
+    for (interaction, mut color, button) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => button.item.step(&mut settings, button.up),
+            Interaction::Hovered => *color = HOVERED_BUTTON.into(),
+            Interaction::None => *color = NORMAL_BUTTON.into(),
+        }
+    }
+}
+
+fn key_binding_button_system(
+    interaction_query: Query<(&Interaction, &KeyBindingButton), Changed<Interaction>>,
+    mut menu: ResMut<SettingsMenu>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction == Interaction::Clicked {
+            menu.rebinding = Some(button.0);
+        }
+    }
+}
+
+/// Bind the next key press to the action waiting for it. Escape cancels.
+fn rebind_key_system(
+    keys: Res<Input<KeyCode>>,
+    mut menu: ResMut<SettingsMenu>,
+    mut settings: ResMut<Settings>,
+) {
+    let action = if let Some(action) = menu.rebinding {
+        action
+    } else {
+        return;
+    };
+    if let Some(key) = keys.get_just_pressed().next() {
+        if *key != KeyCode::Escape {
+            // An action already bound to the key takes over the old key, so that no key is
+            // bound twice
+            let old_key = settings.key_bindings.get(action);
+            if let Some(other) = KeyAction::ALL
+                .into_iter()
+                .find(|other| *other != action && settings.key_bindings.get(*other) == *key)
+            {
+                settings.key_bindings.set(other, old_key);
+            }
+            settings.key_bindings.set(action, *key);
+        }
+        menu.rebinding = None;
+    }
+}
+
+fn close_settings_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<CloseSettingsButton>),
+    >,
+    mut menu: ResMut<SettingsMenu>,
+) {
+    for (interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                menu.open = false;
+                menu.rebinding = None;
+            }
+            Interaction::Hovered => *color = HOVERED_BUTTON.into(),
+            Interaction::None => *color = NORMAL_BUTTON.into(),
+        }
+    }
+}
+
+fn update_settings_text(
+    settings: Res<Settings>,
+    menu: Res<SettingsMenu>,
+    mut query_items: Query<(&SettingsItemText, &mut Text)>,
+    mut query_keys: Query<(&KeyBindingButton, &Children, &mut UiColor)>,
+    mut query_text: Query<&mut Text, Without<SettingsItemText>>,
+) {
+    if !settings.is_changed() && !menu.is_changed() {
+        return;
+    }
+    for (item, mut text) in query_items.iter_mut() {
+        text.sections[0].value = item.0.text(&settings);
+    }
+    for (button, children, mut color) in query_keys.iter_mut() {
+        let rebinding = menu.rebinding == Some(button.0);
+        *color = if rebinding {
+            REBINDING_BUTTON.into()
+        } else {
+            NORMAL_BUTTON.into()
+        };
+        if let Some(mut text) = children
+            .first()
+            .and_then(|child| query_text.get_mut(*child).ok())
+        {
+            text.sections[0].value = if rebinding {
+                format!("{}: press a key...", button.0.label())
+            } else {
+                format!(
+                    "{}: {:?}",
+                    button.0.label(),
+                    settings.key_bindings.get(button.0)
+                )
+            };
+        }
+    }
+}
+
+/// Scale the font size of every UI text by the UI scale setting.
+fn ui_scale_system(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut query: Query<(Entity, &mut Text, Option<&BaseFontSize>), With<Node>>,
+) {
+    for (entity, mut text, base) in query.iter_mut() {
+        let new_base;
+        let base = if let Some(base) = base {
+            &base.0
+        } else {
+            new_base = text
+                .sections
+                .iter()
+                .map(|section| section.style.font_size)
+                .collect::<Vec<_>>();
+            commands
+                .entity(entity)
+                .insert(BaseFontSize(new_base.clone()));
+            &new_base
+        };
+
+        // Check before borrowing mutably to avoid triggering change detection every frame
+        if text
+            .sections
+            .iter()
+            .zip(base)
+            .any(|(section, base)| section.style.font_size != base * settings.ui_scale)
+        {
+            for (section, base) in text.sections.iter_mut().zip(base) {
+                section.style.font_size = base * settings.ui_scale;
+            }
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use bevy::{prelude::*, ui::FocusPolicy};
+
+use crate::{command::PlayerCommand, replay::Playback, Level};
+
+use super::{quit::HOVERED_BUTTON, BUTTON_HEIGHT, PADDING_PX, SCOREBOARD_FONT_SIZE, TEXT_COLOR};
+
+const START_BUTTON_WIDTH: f32 = 200.;
+const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.3, 0.15);
+
+/// The row holding the button, shown during the build phase
+#[derive(Component)]
+struct StartButtonRow;
+
+#[derive(Component)]
+struct StartButton;
+
+#[derive(Component)]
+struct StartButtonText;
+
+pub(super) fn build_start_button(app: &mut App) {
+    app.add_startup_system(add_start_button);
+    app.add_system(start_button_system);
+    app.add_system(show_start_button_system);
+}
+
+fn add_start_button(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Px(BUTTON_HEIGHT)),
+                justify_content: JustifyContent::Center,
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: PADDING_PX,
+                    left: Val::Px(0.),
+                    ..default()
+                },
+                display: Display::None,
+                ..default()
+            },
+            color: Color::NONE.into(),
+            focus_policy: FocusPolicy::Pass,
+            ..default()
+        })
+        .insert(StartButtonRow)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(START_BUTTON_WIDTH), Val::Px(BUTTON_HEIGHT)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    color: NORMAL_BUTTON.into(),
+                    ..default()
+                })
+                .insert(StartButton)
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                "Start",
+                                TextStyle {
+                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                    font_size: SCOREBOARD_FONT_SIZE,
+                                    color: TEXT_COLOR,
+                                },
+                                Default::default(),
+                            ),
+                            ..default()
+                        })
+                        .insert(StartButtonText);
+                });
+        });
+}
+
+fn start_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<Button>, With<StartButton>),
+    >,
+    mut writer: EventWriter<PlayerCommand>,
+    playback: Res<Playback>,
+) {
+    for (interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked if playback.is_playing() => {}
+            Interaction::Clicked => {
+                writer.send(PlayerCommand::StartWave);
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+/// Show the button with the remaining seconds of the countdown during the build phase.
+fn show_start_button_system(
+    level: Res<Level>,
+    mut row_query: Query<&mut Style, With<StartButtonRow>>,
+    mut text_query: Query<&mut Text, With<StartButtonText>>,
+) {
+    let (display, label) = match level.as_ref() {
+        Level::Build {
+            countdown: Some(countdown),
+            ..
+        } => (
+            Display::Flex,
+            format!(
+                "Start ({:.0})",
+                (countdown.duration() - countdown.elapsed())
+                    .as_secs_f32()
+                    .ceil()
+            ),
+        ),
+        Level::Build {
+            countdown: None, ..
+        } => (Display::Flex, "Start".to_string()),
+        _ => (Display::None, "Start".to_string()),
+    };
+    for mut style in row_query.iter_mut() {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+    for mut text in text_query.iter_mut() {
+        if text.sections[0].value != label {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use bevy::prelude::*;
+
+use crate::{
+    mouse::TowerSelection,
+    tower::{
+        Amplifier, BeamTower, CryoTower, Healer, MineLayer, MissileShooter, Shotgun, Tower,
+        TowerLevel, TowerScore,
+    },
+    Health,
+};
+
+use super::{spawn_text, PADDING, STATUS_FONT_SIZE, TEXT_COLOR};
+
+/// Rows beyond this are not shown, so sort by health to find the weakest towers
+const MAX_ROWS: usize = 10;
+
+const NORMAL_ROW: Color = Color::rgba(0.15, 0.15, 0.15, 0.8);
+const HOVERED_ROW: Color = Color::rgba(0.25, 0.25, 0.25, 0.8);
+const SELECTED_ROW: Color = Color::rgba(0.2, 0.45, 0.2, 0.8);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SortKey {
+    Type,
+    Level,
+    Kills,
+    Health,
+}
+
+impl SortKey {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Type => "Type",
+            Self::Level => "Level",
+            Self::Kills => "Kills",
+            Self::Health => "Health",
+        }
+    }
+}
+
+struct TowerListSort {
+    key: SortKey,
+    descending: bool,
+}
+
+#[derive(Component)]
+struct TowerListSummary;
+
+#[derive(Component)]
+struct SortButton(SortKey);
+
+#[derive(Component)]
+struct TowerListRow {
+    index: usize,
+    tower: Option<Entity>,
+}
+
+type TowerKind<'a> = (
+    Option<&'a Shotgun>,
+    Option<&'a Healer>,
+    Option<&'a MissileShooter>,
+    Option<&'a BeamTower>,
+    Option<&'a CryoTower>,
+    Option<&'a MineLayer>,
+    Option<&'a Amplifier>,
+);
+
+fn tower_type_name(
+    (shotgun, healer, missile_tower, beam_tower, cryo_tower, mine_layer, amplifier): TowerKind,
+) -> &'static str {
+    if shotgun.is_some() {
+        "Shotgun"
+    } else if healer.is_some() {
+        "Healer"
+    } else if missile_tower.is_some() {
+        "Missile"
+    } else if beam_tower.is_some() {
+        "Beam"
+    } else if cryo_tower.is_some() {
+        "Cryo"
+    } else if mine_layer.is_some() {
+        "Mines"
+    } else if amplifier.is_some() {
+        "Amplifier"
+    } else {
+        "Turret"
+    }
+}
+
+pub(super) fn build_tower_list(app: &mut App) {
+    app.insert_resource(TowerListSort {
+        key: SortKey::Health,
+        descending: false,
+    });
+    app.add_startup_system(add_tower_list_panel);
+    app.add_system(sort_button_system);
+    app.add_system(tower_list_row_system);
+    app.add_system(update_tower_list);
+}
+
+fn row_text_style(asset_server: &AssetServer) -> TextStyle {
+    TextStyle {
+        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+        font_size: STATUS_FONT_SIZE,
+        color: TEXT_COLOR,
+    }
+}
+
+fn add_tower_list_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                align_items: AlignItems::FlexStart,
+                flex_direction: FlexDirection::ColumnReverse,
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    // Above the progress bar
+                    bottom: Val::Px(PADDING * 2. + 30.),
+                    left: Val::Px(PADDING),
+                    ..default()
+                },
+                padding: Rect::all(Val::Px(2.)),
+                ..default()
+            },
+            color: Color::rgba(0., 0., 0., 0.5).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            spawn_text(&asset_server, parent, &["Towers: ", ""], |mut parent| {
+                parent.insert(TowerListSummary);
+            });
+
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    color: Color::NONE.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for key in [
+                        SortKey::Type,
+                        SortKey::Level,
+                        SortKey::Kills,
+                        SortKey::Health,
+                    ] {
+                        parent
+                            .spawn_bundle(ButtonBundle {
+                                style: Style {
+                                    margin: Rect::all(Val::Px(1.)),
+                                    padding: Rect::all(Val::Px(2.)),
+                                    ..default()
+                                },
+                                color: NORMAL_ROW.into(),
+                                ..default()
+                            })
+                            .insert(SortButton(key))
+                            .with_children(|parent| {
+                                parent.spawn_bundle(TextBundle {
+                                    text: Text::with_section(
+                                        key.label(),
+                                        row_text_style(&asset_server),
+                                        default(),
+                                    ),
+                                    ..default()
+                                });
+                            });
+                    }
+                });
+
+            for index in 0..MAX_ROWS {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            margin: Rect::all(Val::Px(1.)),
+                            padding: Rect::all(Val::Px(2.)),
+                            display: Display::None,
+                            ..default()
+                        },
+                        color: NORMAL_ROW.into(),
+                        ..default()
+                    })
+                    .insert(TowerListRow { index, tower: None })
+                    .with_children(|parent| {
+                        parent.spawn_bundle(TextBundle {
+                            text: Text::with_section("", row_text_style(&asset_server), default()),
+                            ..default()
+                        });
+                    });
+            }
+        });
+}
+
+/// Clicking the column of the current sort key flips the order.
+fn sort_button_system(
+    interaction_query: Query<(&Interaction, &SortButton), Changed<Interaction>>,
+    mut sort: ResMut<TowerListSort>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction == Interaction::Clicked {
+            if sort.key == button.0 {
+                sort.descending = !sort.descending;
+            } else {
+                sort.key = button.0;
+                // Weakest towers first, but the strongest for the other columns
+                sort.descending = button.0 != SortKey::Health && button.0 != SortKey::Type;
+            }
+        }
+    }
+}
+
+/// Clicking a row selects the tower, or toggles it with shift.
+fn tower_list_row_system(
+    interaction_query: Query<(&Interaction, &TowerListRow), Changed<Interaction>>,
+    keys: Res<Input<KeyCode>>,
+    mut selection: ResMut<TowerSelection>,
+) {
+    for (interaction, row) in interaction_query.iter() {
+        if let (Interaction::Clicked, Some(tower)) = (interaction, row.tower) {
+            if keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift) {
+                selection.toggle(tower);
+            } else {
+                selection.0 = vec![tower];
+            }
+        }
+    }
+}
+
+fn update_tower_list(
+    selection: Res<TowerSelection>,
+    sort: Res<TowerListSort>,
+    query_towers: Query<(Entity, &TowerLevel, &TowerScore, &Health, TowerKind), With<Tower>>,
+    mut query_summary: Query<&mut Text, With<TowerListSummary>>,
+    query_sort_buttons: Query<(&SortButton, &Children)>,
+    mut query_rows: Query<(
+        &mut TowerListRow,
+        &mut Style,
+        &mut UiColor,
+        &Interaction,
+        &Children,
+    )>,
+    mut query_text: Query<&mut Text, Without<TowerListSummary>>,
+) {
+    let mut towers: Vec<_> = query_towers
+        .iter()
+        .map(|(entity, level, score, health, kind)| {
+            (
+                entity,
+                tower_type_name(kind),
+                level.level,
+                score.kills,
+                health,
+            )
+        })
+        .collect();
+
+    // The aggregate covers the selection, or all the towers if nothing is selected
+    let aggregated: Vec<_> = towers
+        .iter()
+        .filter(|tower| selection.0.is_empty() || selection.0.contains(&tower.0))
+        .collect();
+    if let Ok(mut text) = query_summary.get_single_mut() {
+        let count = aggregated.len();
+        let kills: usize = aggregated.iter().map(|tower| tower.3).sum();
+        let average_level = if count == 0 {
+            0.
+        } else {
+            aggregated.iter().map(|tower| tower.2).sum::<usize>() as f32 / count as f32
+        };
+        text.sections[1].value = format!(
+            "{} {}, kills {}, avg level {:.1}",
+            count,
+            if selection.0.is_empty() {
+                "total"
+            } else {
+                "selected"
+            },
+            kills,
+            average_level
+        );
+    }
+
+    towers.sort_by(|a, b| match sort.key {
+        SortKey::Type => a.1.cmp(b.1),
+        SortKey::Level => a.2.cmp(&b.2),
+        SortKey::Kills => a.3.cmp(&b.3),
+        SortKey::Health => (a.4.val / a.4.max)
+            .partial_cmp(&(b.4.val / b.4.max))
+            .unwrap_or(std::cmp::Ordering::Equal),
+    });
+    if sort.descending {
+        towers.reverse();
+    }
+
+    if sort.is_changed() {
+        for (button, children) in query_sort_buttons.iter() {
+            if let Some(mut text) = children
+                .first()
+                .and_then(|child| query_text.get_mut(*child).ok())
+            {
+                text.sections[0].value = if button.0 == sort.key {
+                    format!(
+                        "{} {}",
+                        button.0.label(),
+                        if sort.descending { "v" } else { "^" }
+                    )
+                } else {
+                    button.0.label().to_string()
+                };
+            }
+        }
+    }
+
+    for (mut row, mut style, mut color, interaction, children) in query_rows.iter_mut() {
+        let tower = towers.get(row.index);
+        row.tower = tower.map(|tower| tower.0);
+
+        let display = if tower.is_some() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        // Avoid triggering the layout every frame
+        if style.display != display {
+            style.display = display;
+        }
+
+        let (entity, name, level, kills, health) = if let Some(tower) = tower {
+            tower
+        } else {
+            continue;
+        };
+
+        *color = if selection.0.contains(entity) {
+            SELECTED_ROW.into()
+        } else if *interaction == Interaction::Hovered {
+            HOVERED_ROW.into()
+        } else {
+            NORMAL_ROW.into()
+        };
+
+        if let Some(mut text) = children
+            .first()
+            .and_then(|child| query_text.get_mut(*child).ok())
+        {
+            text.sections[0].value = format!(
+                "{:<9} Lv{:>2} Kills{:>5} HP {:.0}/{:.0}",
+                name, level, kills, health.val, health.max
+            );
+        }
+    }
+}
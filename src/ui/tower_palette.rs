@@ -1,9 +1,16 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    mouse::{MouseCursor, SelectedTower, SelectedTowerProps},
+    damage::DamageType,
+    mouse::{HoveringTrashcan, PendingPlacement, SelectedTower},
+    replay::Playback,
     tower::{
-        spawn_beam_tower, spawn_healer, spawn_missile_tower, spawn_shotgun, spawn_turret, Tower,
+        spawn_amplifier, spawn_beam_tower, spawn_cryo_tower, spawn_healer, spawn_mine_layer,
+        spawn_missile_tower, spawn_shotgun, spawn_turret, Amplifier, BeamTower, CryoTower, Healer,
+        MineLayer, MissileShooter, Shotgun, Tower, AMPLIFIER_COLOR, BEAM_RANGE, BEAM_TOWER_SIZE,
+        CRYO_TOWER_COLOR, HEALER_RANGE, MINE_DROP_RANGE, MINE_LAYER_COLOR, MISSILE_TOWER_SIZE,
+        TOWER_SIZE,
     },
     Level, Scoreboard,
 };
@@ -24,17 +31,46 @@ pub(super) fn build_tower_palette(app: &mut App) {
     app.add_system(trashcan_tooltip_system);
 }
 
-#[derive(Component, Debug)]
-enum TowerPalette {
+pub(crate) type TowerTypeQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Option<&'static Shotgun>,
+        Option<&'static Healer>,
+        Option<&'static MissileShooter>,
+        Option<&'static BeamTower>,
+        Option<&'static CryoTower>,
+        Option<&'static MineLayer>,
+        Option<&'static Amplifier>,
+    ),
+    With<Tower>,
+>;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum TowerPalette {
     Turret,
     Shotgun,
     Healer,
     BeamTower,
     MissileTower,
+    CryoTower,
+    MineLayer,
+    Amplifier,
 }
 
 impl TowerPalette {
-    fn spawn(
+    const ALL: [Self; 8] = [
+        Self::Turret,
+        Self::Shotgun,
+        Self::Healer,
+        Self::BeamTower,
+        Self::MissileTower,
+        Self::CryoTower,
+        Self::MineLayer,
+        Self::Amplifier,
+    ];
+
+    pub(crate) fn spawn(
         &self,
         commands: &mut Commands,
         asset_server: &Res<AssetServer>,
@@ -48,16 +84,94 @@ impl TowerPalette {
             Self::MissileTower => {
                 spawn_missile_tower(commands, asset_server, position, 0., default())
             }
+            Self::CryoTower => spawn_cryo_tower(commands, asset_server, position, 0., default()),
+            Self::MineLayer => spawn_mine_layer(commands, asset_server, position, 0., default()),
+            Self::Amplifier => spawn_amplifier(commands, asset_server, position, 0., default()),
+        }
+    }
+
+    pub(crate) fn size(&self) -> f32 {
+        match self {
+            Self::BeamTower => BEAM_TOWER_SIZE,
+            Self::MissileTower => MISSILE_TOWER_SIZE,
+            _ => TOWER_SIZE,
+        }
+    }
+
+    /// Reach of the tower at the level, if it is limited
+    pub(crate) fn range(&self, level: usize) -> Option<f32> {
+        match self {
+            Self::Healer => Some(HEALER_RANGE),
+            Self::BeamTower => Some(BEAM_RANGE),
+            Self::CryoTower => Some(CryoTower::range_by_level(level)),
+            Self::MineLayer => Some(MINE_DROP_RANGE),
+            Self::Amplifier => Some(Amplifier::range_by_level(level)),
+            Self::Turret | Self::Shotgun | Self::MissileTower => None,
         }
     }
 
-    fn cost(&self, tower_count: usize) -> f64 {
+    /// The type of a spawned tower, told apart by the components as in the save format
+    pub(crate) fn of_tower(query: &TowerTypeQuery, tower: Entity) -> Option<Self> {
+        let (shotgun, healer, missile_tower, beam_tower, cryo_tower, mine_layer, amplifier) =
+            query.get(tower).ok()?;
+        Some(if shotgun.is_some() {
+            Self::Shotgun
+        } else if healer.is_some() {
+            Self::Healer
+        } else if missile_tower.is_some() {
+            Self::MissileTower
+        } else if beam_tower.is_some() {
+            Self::BeamTower
+        } else if cryo_tower.is_some() {
+            Self::CryoTower
+        } else if mine_layer.is_some() {
+            Self::MineLayer
+        } else if amplifier.is_some() {
+            Self::Amplifier
+        } else {
+            Self::Turret
+        })
+    }
+
+    pub(crate) fn icon(&self) -> &'static str {
+        match self {
+            Self::Turret => "turret.png",
+            Self::Shotgun => "shotgun.png",
+            Self::Healer | Self::CryoTower | Self::Amplifier => "healer.png",
+            Self::BeamTower => "beam-tower.png",
+            Self::MissileTower | Self::MineLayer => "missile-tower.png",
+        }
+    }
+
+    fn damage_type(&self) -> Option<DamageType> {
+        match self {
+            Self::Turret | Self::Shotgun => Some(DamageType::Kinetic),
+            Self::Healer | Self::CryoTower | Self::Amplifier => None,
+            Self::BeamTower => Some(DamageType::Energy),
+            Self::MissileTower | Self::MineLayer => Some(DamageType::Explosive),
+        }
+    }
+
+    pub(crate) fn cost(&self, tower_count: usize) -> f64 {
         match self {
             Self::Turret => ((1.5f64).powf(tower_count as f64) * 100.).ceil(),
             Self::Shotgun => ((1.5f64).powf(tower_count as f64) * 150.).ceil(),
             Self::Healer => ((1.5f64).powf(tower_count as f64) * 200.).ceil(),
             Self::BeamTower => ((1.5f64).powf(tower_count as f64) * 350.).ceil(),
             Self::MissileTower => ((1.5f64).powf(tower_count as f64) * 200.).ceil(),
+            Self::CryoTower => ((1.5f64).powf(tower_count as f64) * 250.).ceil(),
+            Self::MineLayer => ((1.5f64).powf(tower_count as f64) * 250.).ceil(),
+            Self::Amplifier => ((1.5f64).powf(tower_count as f64) * 300.).ceil(),
+        }
+    }
+
+    /// Tint of the icon when the tower is affordable
+    fn icon_color(&self) -> Color {
+        match self {
+            Self::CryoTower => CRYO_TOWER_COLOR,
+            Self::MineLayer => MINE_LAYER_COLOR,
+            Self::Amplifier => AMPLIFIER_COLOR,
+            _ => Color::WHITE,
         }
     }
 }
@@ -86,21 +200,9 @@ pub(super) fn add_palette_buttons(commands: &mut Commands, asset_server: &Res<As
             ..default()
         })
         .with_children(|parent| {
-            add_tower_icon(parent, &asset_server, "turret.png", TowerPalette::Turret);
-            add_tower_icon(parent, &asset_server, "shotgun.png", TowerPalette::Shotgun);
-            add_tower_icon(parent, &asset_server, "healer.png", TowerPalette::Healer);
-            add_tower_icon(
-                parent,
-                &asset_server,
-                "beam-tower.png",
-                TowerPalette::BeamTower,
-            );
-            add_tower_icon(
-                parent,
-                &asset_server,
-                "missile-tower.png",
-                TowerPalette::MissileTower,
-            );
+            for palette in TowerPalette::ALL {
+                add_tower_icon(parent, &asset_server, palette.icon(), palette);
+            }
         });
 }
 
@@ -137,83 +239,51 @@ fn add_tower_icon(
         });
 }
 
+/// Clicking a palette icon starts a placement, which the mouse system finishes as a
+/// `PlayerCommand::PlaceTower` when the button is released.
 fn palette_mouse_system(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    windows: Res<Windows>,
     level: Res<Level>,
-    mut scoreboard: ResMut<Scoreboard>,
-    mut query_mouse: Query<(&mut Transform, &mut Visibility), With<MouseCursor>>,
+    scoreboard: Res<Scoreboard>,
     query_towers: Query<&Tower>,
     query_palette: Query<(&Interaction, &Parent, &TowerPalette), Changed<Interaction>>,
     mut query_ui_color: Query<&mut UiColor>,
-    mut selected_tower: ResMut<SelectedTower>,
+    selected_tower: Res<SelectedTower>,
+    mut pending: ResMut<PendingPlacement>,
+    playback: Res<Playback>,
 ) {
     if selected_tower
         .as_ref()
         .as_ref()
         .map(|f| f.dragging)
         .unwrap_or(false)
-        || !level._is_running()
+        || pending.0.is_some()
+        || !level.can_build()
+        || playback.is_playing()
     {
         return;
     }
 
-    let window = if let Some(window) = windows.iter().next() {
-        window
-    } else {
-        return;
-    };
-    let mouse = window.cursor_position();
-
-    if let Some(((mut cursor_transform, mut visibility), mouse_position)) =
-        query_mouse.get_single_mut().ok().zip(mouse)
-    {
-        let (width, height) = (window.width(), window.height());
-        let mouse_screen = Vec2::new(
-            mouse_position.x - width / 2.,
-            mouse_position.y - height / 2.,
-        );
-
-        let tower_count = query_towers.iter().count();
-
-        // println!("Mouse: {:?} -> {:?}", mouse_position, mouse_screen);
-        for (interaction, parent, palette) in query_palette.iter() {
-            if let Ok(mut ui_color) = query_ui_color.get_component_mut::<UiColor>(**parent) {
-                // println!("Has ui_color: {ui_color:?}");
-                match *interaction {
-                    Interaction::Clicked => {
-                        println!("Clicked tower palette at {mouse_screen:?}");
-
-                        let cost = palette.cost(tower_count);
-                        if scoreboard.credits < cost {
-                            return;
-                        }
-
-                        *ui_color = Color::rgba(1., 0., 1., 0.75).into();
-
-                        visibility.is_visible = true;
-                        *cursor_transform =
-                            Transform::from_xyz(mouse_screen.x, mouse_screen.y, 0.2)
-                                .with_scale(Vec3::new(2., 2., 1.));
-
-                        let tower = palette.spawn(&mut commands, &asset_server, mouse_screen);
-                        *selected_tower = Some(SelectedTowerProps {
-                            tower,
-                            dragging: true,
-                            hovering_trashcan: false,
-                        });
-
-                        scoreboard.credits -= cost;
+    let tower_count = query_towers.iter().count();
 
+    for (interaction, parent, palette) in query_palette.iter() {
+        if let Ok(mut ui_color) = query_ui_color.get_component_mut::<UiColor>(**parent) {
+            match *interaction {
+                Interaction::Clicked => {
+                    // Checked again when placing, but don't start a placement that can't finish
+                    if scoreboard.credits < palette.cost(tower_count) {
                         return;
                     }
-                    Interaction::Hovered => {
-                        *ui_color = Color::rgba(0.5, 0., 0., 0.5).into();
-                    }
-                    Interaction::None => {
-                        *ui_color = Color::rgba(0.0, 0., 0., 0.5).into();
-                    }
+
+                    *ui_color = Color::rgba(1., 0., 1., 0.75).into();
+                    pending.0 = Some(*palette);
+
+                    return;
+                }
+                Interaction::Hovered => {
+                    *ui_color = Color::rgba(0.5, 0., 0., 0.5).into();
+                }
+                Interaction::None => {
+                    *ui_color = Color::rgba(0.0, 0., 0., 0.5).into();
                 }
             }
         }
@@ -230,7 +300,7 @@ fn update_palette_system(
         *color = if scoreboard.credits < palette.cost(tower_count) {
             Color::rgba(0.5, 0.5, 0.5, 0.5).into()
         } else {
-            Color::WHITE.into()
+            palette.icon_color().into()
         };
     }
 }
@@ -241,6 +311,9 @@ struct PaletteTooltipText;
 #[derive(Component)]
 struct PaletteTooltipTowerType;
 
+#[derive(Component)]
+struct PaletteTooltipDamageType;
+
 fn add_palette_tooltip_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands
         .spawn_bundle(NodeBundle {
@@ -250,7 +323,7 @@ fn add_palette_tooltip_panel(mut commands: Commands, asset_server: Res<AssetServ
                 flex_direction: FlexDirection::Column,
                 position_type: PositionType::Absolute,
                 position: Rect {
-                    top: Val::Px(PADDING * 4. + BUTTON_HEIGHT + STATUS_FONT_SIZE * 6.),
+                    top: Val::Px(PADDING * 4. + BUTTON_HEIGHT + STATUS_FONT_SIZE * 9.),
                     right: Val::Px(PADDING * 2. + PALETTE_SIZE),
                     ..default()
                 },
@@ -271,6 +344,17 @@ fn add_palette_tooltip_panel(mut commands: Commands, asset_server: Res<AssetServ
             spawn_text(&asset_server, parent, &["Cost: ", ""], |mut parent| {
                 parent.insert(PaletteTooltipText);
             });
+
+            spawn_text(
+                &asset_server,
+                parent,
+                &["Damage type: ", ""],
+                |mut parent| {
+                    parent
+                        .insert(PaletteTooltipText)
+                        .insert(PaletteTooltipDamageType);
+                },
+            );
         });
 }
 
@@ -280,11 +364,27 @@ fn palette_tooltip_system(
     mut query_tooltip_visible: Query<&mut Visibility, With<PaletteTooltipText>>,
     mut query_tooltip_tower_type: Query<
         &mut Text,
-        (With<PaletteTooltipText>, With<PaletteTooltipTowerType>),
+        (
+            With<PaletteTooltipText>,
+            With<PaletteTooltipTowerType>,
+            Without<PaletteTooltipDamageType>,
+        ),
     >,
     mut query_tooltip_cost: Query<
         &mut Text,
-        (With<PaletteTooltipText>, Without<PaletteTooltipTowerType>),
+        (
+            With<PaletteTooltipText>,
+            Without<PaletteTooltipTowerType>,
+            Without<PaletteTooltipDamageType>,
+        ),
+    >,
+    mut query_tooltip_damage_type: Query<
+        &mut Text,
+        (
+            With<PaletteTooltipText>,
+            Without<PaletteTooltipTowerType>,
+            With<PaletteTooltipDamageType>,
+        ),
     >,
 ) {
     let tower_count = query_towers.iter().count();
@@ -300,6 +400,13 @@ fn palette_tooltip_system(
                 if let Ok(mut text) = query_tooltip_cost.get_single_mut() {
                     text.sections[1].value = format!("${}", palette.cost(tower_count));
                 }
+                if let Ok(mut text) = query_tooltip_damage_type.get_single_mut() {
+                    text.sections[1].value = if let Some(damage_type) = palette.damage_type() {
+                        format!("{}", damage_type)
+                    } else {
+                        "-".to_string()
+                    };
+                }
             }
             Interaction::None => {
                 for mut visibility in query_tooltip_visible.iter_mut() {
@@ -373,7 +480,7 @@ fn trashcan_tooltip_system(
     query_trashcan: Query<(&Interaction, &Parent), (With<TowerTrashcan>, Changed<Interaction>)>,
     mut query_tooltip_visible: Query<&mut Visibility, With<TrashcanTooltipText>>,
     mut query_ui_color: Query<&mut UiColor>,
-    mut selected_tower: ResMut<SelectedTower>,
+    mut hovering_trashcan: ResMut<HoveringTrashcan>,
 ) {
     for (interaction, parent) in query_trashcan.iter() {
         match *interaction {
@@ -384,9 +491,7 @@ fn trashcan_tooltip_system(
                 if let Ok(mut ui_color) = query_ui_color.get_mut(**parent) {
                     *ui_color = Color::rgba(0.5, 0., 0., 0.5).into();
                 }
-                if let Some(selected_tower) = selected_tower.as_mut() {
-                    selected_tower.hovering_trashcan = true;
-                }
+                hovering_trashcan.0 = true;
             }
             Interaction::None => {
                 for mut visibility in query_tooltip_visible.iter_mut() {
@@ -395,9 +500,7 @@ fn trashcan_tooltip_system(
                 if let Ok(mut ui_color) = query_ui_color.get_mut(**parent) {
                     *ui_color = Color::rgba(0.0, 0., 0., 0.5).into();
                 }
-                if let Some(selected_tower) = selected_tower.as_mut() {
-                    selected_tower.hovering_trashcan = false;
-                }
+                hovering_trashcan.0 = false;
             }
             _ => (),
         }
@@ -2,8 +2,13 @@ use bevy::prelude::*;
 
 use crate::{
     bullet::BulletShooter,
-    mouse::SelectedTower,
-    tower::{tower_max_exp, BeamTower, Healer, TowerLevel, TowerScore},
+    damage::DamageType,
+    mouse::{SelectedTower, TowerSelection},
+    stats::{ModifierSource, Stat, Stats},
+    tower::{
+        tower_max_exp, AimMode, Amplifier, BeamTower, CryoTower, Healer, MineLayer, Targeting,
+        TowerLevel, TowerScore,
+    },
     Health,
 };
 
@@ -24,6 +29,15 @@ struct TowerExpText;
 #[derive(Component)]
 struct TowerShooterText;
 
+#[derive(Component)]
+struct TowerDamageTypeText;
+
+#[derive(Component)]
+struct TowerTargetingText;
+
+#[derive(Component)]
+struct TowerAimText;
+
 pub(super) fn build_tower_status(app: &mut App) {
     app.add_startup_system(add_status_panel);
     app.add_system(update_tower_scoreboard);
@@ -31,6 +45,9 @@ pub(super) fn build_tower_status(app: &mut App) {
     app.add_system(update_tower_level);
     app.add_system(update_tower_experience);
     app.add_system(update_tower_damage);
+    app.add_system(update_tower_damage_type);
+    app.add_system(update_tower_targeting);
+    app.add_system(update_tower_aim);
 }
 
 fn add_status_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
@@ -72,19 +89,41 @@ fn add_status_panel(mut commands: Commands, asset_server: Res<AssetServer>) {
             spawn_text(&asset_server, parent, &["Damage: ", ""], |mut parent| {
                 parent.insert(TowerShooterText);
             });
+
+            spawn_text(&asset_server, parent, &["Type: ", ""], |mut parent| {
+                parent.insert(TowerDamageTypeText);
+            });
+
+            spawn_text(&asset_server, parent, &["Targeting: ", ""], |mut parent| {
+                parent.insert(TowerTargetingText);
+            });
+
+            spawn_text(&asset_server, parent, &["Aim: ", ""], |mut parent| {
+                parent.insert(TowerAimText);
+            });
         });
 }
 
+/// The tower under the cursor, or the only selected tower if none is
+fn shown_tower(selected_tower: &SelectedTower, selection: &TowerSelection) -> Option<Entity> {
+    selected_tower
+        .as_ref()
+        .map(|tower| tower.tower)
+        .or_else(|| match selection.0[..] {
+            [tower] => Some(tower),
+            _ => None,
+        })
+}
+
 fn update_tower_scoreboard(
     selected_tower: Res<SelectedTower>,
+    selection: Res<TowerSelection>,
     tower_score_query: Query<&TowerScore>,
     mut text_query: Query<&mut Text, With<TowerScoreText>>,
 ) {
     if let Ok(mut text) = text_query.get_single_mut() {
-        if let Some(selected_tower) = selected_tower
-            .as_ref()
-            .as_ref()
-            .and_then(|tower| tower_score_query.get(tower.tower).ok())
+        if let Some(selected_tower) = shown_tower(&selected_tower, &selection)
+            .and_then(|tower| tower_score_query.get(tower).ok())
         {
             text.sections[1].value = format!("{:?}", selected_tower.kills);
         } else {
@@ -95,14 +134,13 @@ fn update_tower_scoreboard(
 
 fn update_tower_health(
     selected_tower: Res<SelectedTower>,
+    selection: Res<TowerSelection>,
     tower_health_query: Query<&Health>,
     mut text_query: Query<&mut Text, With<TowerHealthText>>,
 ) {
     if let Ok(mut text) = text_query.get_single_mut() {
-        if let Some(health) = selected_tower
-            .as_ref()
-            .as_ref()
-            .and_then(|tower| tower_health_query.get(tower.tower).ok())
+        if let Some(health) = shown_tower(&selected_tower, &selection)
+            .and_then(|tower| tower_health_query.get(tower).ok())
         {
             text.sections[1].value = format!("{:.0}/{:.0}", health.val, health.max);
         } else {
@@ -113,14 +151,13 @@ fn update_tower_health(
 
 fn update_tower_level(
     selected_tower: Res<SelectedTower>,
+    selection: Res<TowerSelection>,
     tower_level_query: Query<&TowerLevel>,
     mut text_query: Query<&mut Text, With<TowerLevelText>>,
 ) {
     if let Ok(mut text) = text_query.get_single_mut() {
-        if let Some(selected_tower) = selected_tower
-            .as_ref()
-            .as_ref()
-            .and_then(|tower| tower_level_query.get(tower.tower).ok())
+        if let Some(selected_tower) = shown_tower(&selected_tower, &selection)
+            .and_then(|tower| tower_level_query.get(tower).ok())
         {
             text.sections[1].value = format!("{:?}", selected_tower.level);
         } else {
@@ -131,14 +168,13 @@ fn update_tower_level(
 
 fn update_tower_experience(
     selected_tower: Res<SelectedTower>,
+    selection: Res<TowerSelection>,
     tower_level_query: Query<&TowerLevel>,
     mut text_query: Query<&mut Text, With<TowerExpText>>,
 ) {
     if let Ok(mut text) = text_query.get_single_mut() {
-        if let Some(tower_level) = selected_tower
-            .as_ref()
-            .as_ref()
-            .and_then(|tower| tower_level_query.get(tower.tower).ok())
+        if let Some(tower_level) = shown_tower(&selected_tower, &selection)
+            .and_then(|tower| tower_level_query.get(tower).ok())
         {
             text.sections[1].value =
                 format!("{}/{}", tower_level.exp, tower_max_exp(tower_level.level));
@@ -148,36 +184,151 @@ fn update_tower_experience(
     }
 }
 
+/// Format a stat with the value buffed by auras next to it if any is active
+fn format_buffed(stats: &Stats, stat: Stat) -> String {
+    let base = stats.get_without(stat, ModifierSource::Aura);
+    let buffed = stats.get(stat);
+    if base == buffed {
+        format!("{:.2}", buffed)
+    } else {
+        format!("{:.2} -> {:.2}", base, buffed)
+    }
+}
+
 fn update_tower_damage(
     selected_tower: Res<SelectedTower>,
+    selection: Res<TowerSelection>,
     tower_shooter_query: Query<(
         Option<&BulletShooter>,
         Option<&Healer>,
         &TowerLevel,
+        &Stats,
         Option<&BeamTower>,
+        Option<&CryoTower>,
+        Option<&MineLayer>,
+        Option<&Amplifier>,
     )>,
     mut text_query: Query<&mut Text, With<TowerShooterText>>,
 ) {
     if let Ok(mut text) = text_query.get_single_mut() {
-        match selected_tower
-            .as_ref()
-            .as_ref()
-            .and_then(|tower| tower_shooter_query.get(tower.tower).ok())
-        {
-            Some((Some(tower_shooter), None, _, None)) => {
-                text.sections[0].value = "Damage: ".to_string();
-                text.sections[1].value = format!("{:.2}", tower_shooter.damage)
-            }
-            Some((None, Some(healer), _, None)) => {
-                text.sections[0].value = "Heal: ".to_string();
-                text.sections[1].value = format!("{:.2}", healer.heal_amt);
+        let (bullet_shooter, healer, level, stats, beam_tower, cryo_tower, mine_layer, amplifier) =
+            if let Some(tower) = shown_tower(&selected_tower, &selection)
+                .and_then(|tower| tower_shooter_query.get(tower).ok())
+            {
+                tower
+            } else {
+                text.sections[1].value = "".to_string();
+                return;
+            };
+
+        let level = level.level;
+        let (label, mut value) = if let Some(bullet_shooter) = bullet_shooter {
+            let mut value = format_buffed(stats, Stat::Damage);
+            if 0 < bullet_shooter.pierce {
+                value += &format!(" pierce {}", bullet_shooter.pierce);
             }
-            Some((None, None, level, Some(_))) => {
-                text.sections[0].value = "DPS: ".to_string();
-                text.sections[1].value =
-                    format!("{:.2}", BeamTower::beam_dps_by_level(level.level));
+            if 0 < bullet_shooter.ricochet {
+                value += &format!(" ricochet {}", bullet_shooter.ricochet);
             }
-            _ => text.sections[1].value = "".to_string(),
+            ("Damage: ", value)
+        } else if healer.is_some() {
+            ("Heal: ", format_buffed(stats, Stat::HealAmount))
+        } else if beam_tower.is_some() {
+            ("DPS: ", format_buffed(stats, Stat::Damage))
+        } else if cryo_tower.is_some() {
+            (
+                "Slow: ",
+                format!(
+                    "{:.0}% range {:.0}",
+                    CryoTower::slow_by_level(level) * 100.,
+                    CryoTower::range_by_level(level)
+                ),
+            )
+        } else if mine_layer.is_some() {
+            (
+                "Mine: ",
+                format!(
+                    "{} max {}",
+                    format_buffed(stats, Stat::Damage),
+                    MineLayer::max_mines_by_level(level)
+                ),
+            )
+        } else if amplifier.is_some() {
+            let (damage, fire_rate) = Amplifier::buff_by_level(level);
+            (
+                "Buff: ",
+                format!(
+                    "damage +{:.0}% rate +{:.0}%",
+                    (damage - 1.) * 100.,
+                    (fire_rate - 1.) * 100.
+                ),
+            )
+        } else {
+            ("", "".to_string())
+        };
+
+        let fire_rate = stats.get(Stat::FireRate);
+        if fire_rate != 1. {
+            value += &format!(" rate +{:.0}%", (fire_rate - 1.) * 100.);
+        }
+        text.sections[0].value = label.to_string();
+        text.sections[1].value = value;
+    }
+}
+
+fn update_tower_damage_type(
+    selected_tower: Res<SelectedTower>,
+    selection: Res<TowerSelection>,
+    tower_shooter_query: Query<(
+        Option<&BulletShooter>,
+        Option<&BeamTower>,
+        Option<&MineLayer>,
+    )>,
+    mut text_query: Query<&mut Text, With<TowerDamageTypeText>>,
+) {
+    if let Ok(mut text) = text_query.get_single_mut() {
+        text.sections[1].value = match shown_tower(&selected_tower, &selection)
+            .and_then(|tower| tower_shooter_query.get(tower).ok())
+        {
+            Some((Some(tower_shooter), _, _)) => format!("{}", tower_shooter.damage_type),
+            Some((None, Some(beam_tower), _)) => format!("{}", beam_tower.damage_type),
+            Some((None, None, Some(_))) => format!("{}", DamageType::Explosive),
+            _ => "".to_string(),
+        }
+    }
+}
+
+fn update_tower_targeting(
+    selected_tower: Res<SelectedTower>,
+    selection: Res<TowerSelection>,
+    tower_targeting_query: Query<&Targeting>,
+    mut text_query: Query<&mut Text, With<TowerTargetingText>>,
+) {
+    if let Ok(mut text) = text_query.get_single_mut() {
+        if let Some(targeting) = shown_tower(&selected_tower, &selection)
+            .and_then(|tower| tower_targeting_query.get(tower).ok())
+        {
+            text.sections[1].value = format!("{:?}", targeting);
+        } else {
+            text.sections[1].value = "".to_string();
         }
     }
 }
+
+fn update_tower_aim(
+    selected_tower: Res<SelectedTower>,
+    selection: Res<TowerSelection>,
+    tower_aim_query: Query<&AimMode>,
+    mut text_query: Query<&mut Text, With<TowerAimText>>,
+) {
+    if let Ok(mut text) = text_query.get_single_mut() {
+        text.sections[1].value = match shown_tower(&selected_tower, &selection)
+            .and_then(|tower| tower_aim_query.get(tower).ok())
+        {
+            Some(AimMode::Auto) => "Auto".to_string(),
+            Some(AimMode::Hold { arc, .. }) => format!("Hold {:.0}°", arc.to_degrees()),
+            Some(AimMode::Manual) => "Manual".to_string(),
+            None => "".to_string(),
+        };
+    }
+}
@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+
+use crate::{
+    command::PlayerCommand,
+    replay::Playback,
+    undo::{undo_window_open, UndoStack},
+    Level,
+};
+
+use super::{
+    pause::PAUSE_BUTTON_WIDTH, quit::HOVERED_BUTTON, BUTTON_HEIGHT, PADDING, SCOREBOARD_FONT_SIZE,
+    TEXT_COLOR,
+};
+
+const NORMAL_BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+
+#[derive(Component)]
+struct UndoButtonFilter;
+
+pub(super) fn build_undo_button(app: &mut App) {
+    app.add_startup_system(add_undo_button);
+    app.add_system(undo_button_system);
+    app.add_system(show_undo_button_system);
+}
+
+fn add_undo_button(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(100.0), Val::Px(BUTTON_HEIGHT)),
+                margin: Rect::all(Val::Auto),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(PADDING),
+                    right: Val::Px(PADDING * 3. + super::quit::BUTTON_WIDTH + PAUSE_BUTTON_WIDTH),
+                    ..default()
+                },
+                display: Display::None,
+                ..default()
+            },
+            color: NORMAL_BUTTON.into(),
+            ..default()
+        })
+        .insert(UndoButtonFilter)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "Undo",
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: SCOREBOARD_FONT_SIZE,
+                        color: TEXT_COLOR,
+                    },
+                    Default::default(),
+                ),
+                ..default()
+            });
+        });
+}
+
+fn undo_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<Button>, With<UndoButtonFilter>),
+    >,
+    mut writer: EventWriter<PlayerCommand>,
+) {
+    for (interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                writer.send(PlayerCommand::Undo);
+            }
+            Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+            }
+        }
+    }
+}
+
+/// The button is shown while there is something to undo.
+fn show_undo_button_system(
+    mut button_query: Query<&mut Style, With<UndoButtonFilter>>,
+    level: Res<Level>,
+    undo_stack: Res<UndoStack>,
+    playback: Res<Playback>,
+) {
+    let display = if undo_window_open(&level) && !undo_stack.0.is_empty() && !playback.is_playing()
+    {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    for mut style in button_query.iter_mut() {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Undoing the tower placements and moves made while setting up a stage.
+
+use crate::{
+    command::PlayerCommand,
+    replay::Playback,
+    settings::{KeyAction, Settings},
+    ClearEvent, Level,
+};
+use bevy::prelude::*;
+
+/// Seconds from the start of a stage during which placements and moves can be undone
+const UNDO_WINDOW: f32 = 10.;
+
+pub(crate) struct UndoPlugin;
+
+impl Plugin for UndoPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(UndoStack::default());
+        app.add_system(undo_key_system);
+        app.add_system(undo_window_system);
+    }
+}
+
+pub(crate) enum UndoEntry {
+    /// A bought tower and the credits paid for it
+    Placed {
+        tower: Entity,
+        cost: f64,
+    },
+    Moved {
+        tower: Entity,
+        from: Vec2,
+    },
+}
+
+#[derive(Default)]
+pub(crate) struct UndoStack(pub Vec<UndoEntry>);
+
+pub(crate) fn undo_window_open(level: &Level) -> bool {
+    match level {
+        Level::Select | Level::Build { .. } => true,
+        Level::Running { timer, .. } => timer.elapsed_secs() < UNDO_WINDOW,
+    }
+}
+
+fn undo_key_system(
+    keys: Res<Input<KeyCode>>,
+    settings: Res<Settings>,
+    playback: Res<Playback>,
+    mut writer: EventWriter<PlayerCommand>,
+) {
+    let ctrl = keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl);
+    if ctrl && !playback.is_playing() && settings.key_bindings.just_pressed(&keys, KeyAction::Undo)
+    {
+        writer.send(PlayerCommand::Undo);
+    }
+}
+
+/// The undo history is only kept during the setup of a stage.
+fn undo_window_system(
+    level: Res<Level>,
+    mut reader: EventReader<ClearEvent>,
+    mut undo_stack: ResMut<UndoStack>,
+) {
+    let cleared = reader.iter().next().is_some();
+    if (cleared || !undo_window_open(&level)) && !undo_stack.0.is_empty() {
+        undo_stack.0.clear();
+    }
+}